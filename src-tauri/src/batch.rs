@@ -0,0 +1,262 @@
+use crate::history::{self, ExportFormat, HistoryRecord, HistoryStatus, UploadStarted};
+use crate::profile::BucketProfile;
+use crate::typ::File;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+// 未结束的批次，键是 batch_id。批次结束后移除，报告从上传历史中读取，应用重启后也能导出
+static PENDING: Lazy<DashMap<String, PendingBatch>> = Lazy::new(DashMap::new);
+
+struct PendingBatch {
+    profile_id: String,
+    bucket: String,
+    // 仍在上传的文件：file_id 到历史记录 id，写入历史失败时为空
+    files: HashMap<String, Option<i64>>,
+}
+
+/// 批次中单个文件的上传结果
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFile {
+    pub file_id: String,
+    pub local_path: Option<String>,
+    pub key: String,
+    pub url: String,
+    pub size: Option<u64>,
+    // 上传内容（压缩、加密前）的 SHA-256
+    pub sha256: Option<String>,
+    pub duration_ms: u64,
+    pub retries: u32,
+    pub status: HistoryStatus,
+    pub error: Option<String>,
+}
+
+/// 批次结束时通过 upload-batch 事件发送的汇总
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub batch_id: String,
+    pub profile_id: String,
+    pub bucket: String,
+    // Unix 时间戳（秒），未结束时 finished_at 为空
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    // 上传成功的文件的总大小
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub summary: BatchSummary,
+    pub files: Vec<BatchFile>,
+}
+
+impl From<&HistoryRecord> for BatchFile {
+    fn from(record: &HistoryRecord) -> Self {
+        Self {
+            file_id: record.file_id.clone(),
+            local_path: record.local_path.clone(),
+            key: record.key.clone(),
+            url: record.url.clone(),
+            size: record.size,
+            sha256: record.sha256.clone(),
+            duration_ms: record.duration_ms.unwrap_or_default(),
+            retries: record
+                .details
+                .as_ref()
+                .map(|details| details.retries)
+                .unwrap_or_default(),
+            status: record.status,
+            error: record.error.clone(),
+        }
+    }
+}
+
+fn summarize(
+    batch_id: &str,
+    profile_id: &str,
+    bucket: &str,
+    records: &[HistoryRecord],
+) -> BatchSummary {
+    let count = |status| records.iter().filter(|r| r.status == status).count();
+    // 应用在上传中途退出时，记录会一直是上传中，批次视为未结束
+    let finished = records.iter().all(|r| r.status != HistoryStatus::Uploading);
+    BatchSummary {
+        batch_id: batch_id.to_string(),
+        profile_id: profile_id.to_string(),
+        bucket: bucket.to_string(),
+        started_at: records
+            .iter()
+            .map(|r| r.created_at)
+            .min()
+            .unwrap_or_default(),
+        finished_at: finished
+            .then(|| records.iter().filter_map(|r| r.finished_at).max())
+            .flatten(),
+        total: records.len(),
+        succeeded: count(HistoryStatus::Success),
+        failed: count(HistoryStatus::Error),
+        cancelled: count(HistoryStatus::Cancelled),
+        total_bytes: records
+            .iter()
+            .filter(|r| r.status == HistoryStatus::Success)
+            .filter_map(|r| r.size)
+            .sum(),
+    }
+}
+
+/// 为一次 r2_upload 创建批次，提前为所有文件写入上传历史，返回 batch_id。
+/// 没有文件时立即发送 upload-batch 事件
+pub async fn start(app: &AppHandle, profile: &BucketProfile, files: &[File]) -> String {
+    let batch_id = Uuid::new_v4().to_string();
+    let uploads = files
+        .iter()
+        .map(|file| UploadStarted {
+            file_id: file.id.clone(),
+            profile_id: profile.id.clone(),
+            bucket: profile.bucket_name.clone(),
+            key: file.remote_filename.clone(),
+            local_path: file.source.local_path().map(str::to_string),
+            batch_id: Some(batch_id.clone()),
+        })
+        .collect();
+    // 历史记录写入失败不影响上传
+    let history_ids = match history::record_start_many(app, uploads).await {
+        Ok(ids) => ids.into_iter().map(Some).collect(),
+        Err(_) => vec![None; files.len()],
+    };
+    let pending: HashMap<_, _> = files
+        .iter()
+        .map(|file| file.id.clone())
+        .zip(history_ids)
+        .collect();
+
+    if pending.is_empty() {
+        let summary = summarize(&batch_id, &profile.id, &profile.bucket_name, &[]);
+        let _ = app.emit("upload-batch", summary);
+    } else {
+        PENDING.insert(
+            batch_id.clone(),
+            PendingBatch {
+                profile_id: profile.id.clone(),
+                bucket: profile.bucket_name.clone(),
+                files: pending,
+            },
+        );
+    }
+    batch_id
+}
+
+/// 批次开始时为文件写入的历史记录 id
+pub fn history_id(batch_id: &str, file_id: &str) -> Option<i64> {
+    PENDING
+        .get(batch_id)
+        .and_then(|batch| batch.files.get(file_id).copied().flatten())
+}
+
+/// 文件的上传结果写入历史后调用，批次中最后一个文件结束时发送 upload-batch 事件
+pub async fn record_finish(app: &AppHandle, batch_id: &str, file_id: &str) {
+    let finished = PENDING
+        .remove_if_mut(batch_id, |_, batch| {
+            batch.files.remove(file_id);
+            batch.files.is_empty()
+        })
+        .map(|(_, batch)| batch);
+    if let Some(batch) = finished {
+        emit_summary(app, batch_id, batch).await;
+    }
+}
+
+/// 取消上传时调用，取消的文件可能属于任意批次
+pub async fn record_cancelled(app: &AppHandle, file_id: &str) {
+    let batch_ids = PENDING
+        .iter()
+        .filter(|entry| entry.files.contains_key(file_id))
+        .map(|entry| entry.key().clone())
+        .collect::<Vec<_>>();
+    for batch_id in batch_ids {
+        record_finish(app, &batch_id, file_id).await;
+    }
+}
+
+async fn emit_summary(app: &AppHandle, batch_id: &str, batch: PendingBatch) {
+    let records = history::batch_records(app, batch_id)
+        .await
+        .unwrap_or_default();
+    let summary = summarize(batch_id, &batch.profile_id, &batch.bucket, &records);
+    let _ = app.emit("upload-batch", summary);
+}
+
+async fn report(app: &AppHandle, batch_id: &str) -> Result<BatchReport, String> {
+    let records = history::batch_records(app, batch_id).await?;
+    let first = records
+        .first()
+        .ok_or_else(|| format!("找不到上传批次：{}", batch_id))?;
+    Ok(BatchReport {
+        summary: summarize(batch_id, &first.profile_id, &first.bucket, &records),
+        files: records.iter().map(BatchFile::from).collect(),
+    })
+}
+
+#[tauri::command]
+pub async fn batch_report(app: AppHandle, batch_id: String) -> Result<BatchReport, String> {
+    report(&app, &batch_id).await
+}
+
+/// 导出批次报告，返回文件数。未结束的批次中仍在上传的文件状态为 uploading
+#[tauri::command]
+pub async fn batch_export(
+    app: AppHandle,
+    batch_id: String,
+    path: String,
+    format: ExportFormat,
+) -> Result<usize, String> {
+    let report = report(&app, &batch_id).await?;
+    let content = match format {
+        ExportFormat::Json => serde_json::to_vec_pretty(&report).map_err(|e| e.to_string())?,
+        ExportFormat::Csv => files_to_csv(&report.files).into_bytes(),
+    };
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("无法写入导出文件：{}", e))?;
+    Ok(report.files.len())
+}
+
+fn files_to_csv(files: &[BatchFile]) -> String {
+    let mut out = history::csv_row(&[
+        "file_id",
+        "local_path",
+        "key",
+        "url",
+        "size",
+        "sha256",
+        "duration_ms",
+        "retries",
+        "status",
+        "error",
+    ]);
+    for file in files {
+        out.push_str(&history::csv_row(&[
+            &file.file_id,
+            file.local_path.as_deref().unwrap_or_default(),
+            &file.key,
+            &file.url,
+            &file.size.map(|size| size.to_string()).unwrap_or_default(),
+            file.sha256.as_deref().unwrap_or_default(),
+            &file.duration_ms.to_string(),
+            &file.retries.to_string(),
+            file.status.as_str(),
+            file.error.as_deref().unwrap_or_default(),
+        ]));
+    }
+    out
+}
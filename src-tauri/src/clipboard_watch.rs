@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+use {
+    crate::profile,
+    crate::r2::{self, R2Client},
+    crate::typ::{File, ObjectHeaders, UploadSource},
+    chrono::Local,
+    once_cell::sync::Lazy,
+    sha2::{Digest, Sha256},
+    std::path::{Path, PathBuf},
+    std::sync::Arc,
+    tauri::{Emitter, EventId, Listener, Manager},
+    tauri_plugin_clipboard::Clipboard,
+    tokio::sync::Mutex,
+    uuid::Uuid,
+};
+
+use tauri::AppHandle;
+
+// 剪贴板插件的监听器在内容变化时发出的事件
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+const MONITOR_EVENT: &str = "plugin:clipboard://clipboard-monitor/update";
+
+// 开启时保存设置，下次启动应用时自动恢复监听
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+const SETTINGS_FILENAME: &str = "clipboard-watch.json";
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+static WATCHER: Lazy<Mutex<Option<Watcher>>> = Lazy::new(|| Mutex::new(None));
+
+/// 剪贴板自动上传的设置，由前端在用户开启时传入
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardWatchSettings {
+    // 上传到的存储桶，通常是默认存储桶
+    pub profile_id: String,
+    // 同时上传复制的文件（不包括文件夹）
+    #[serde(default)]
+    pub include_files: bool,
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+struct Watcher {
+    settings: ClipboardWatchSettings,
+    listener: EventId,
+    // 上次处理的剪贴板内容，写回链接也会触发变化事件，相同内容不重复上传
+    last_content: Option<String>,
+}
+
+/// 开始监听剪贴板，复制图片或文件后自动上传，并把链接写回剪贴板。
+/// 上传失败或无法读取剪贴板时发送 clipboard-watch-error 事件
+#[tauri::command]
+pub async fn clipboard_watch_start(
+    app: AppHandle,
+    settings: ClipboardWatchSettings,
+) -> Result<(), String> {
+    #[cfg(any(target_os = "ios", target_os = "android"))]
+    {
+        let _ = (app, settings);
+        Err("当前平台不支持剪贴板监听".to_string())
+    }
+
+    #[cfg(not(any(target_os = "ios", target_os = "android")))]
+    {
+        // 提前检查存储桶，避免开启后每次上传都失败
+        profile::get_profile(&settings.profile_id).await?;
+        watch(&app, settings.clone()).await?;
+
+        let json = serde_json::to_vec_pretty(&settings).map_err(|e| e.to_string())?;
+        tokio::fs::write(settings_path(&app)?, json)
+            .await
+            .map_err(|e| format!("无法保存剪贴板监听设置：{}", e))
+    }
+}
+
+/// 启动应用时恢复上次开启的监听。配置存储可能尚未解锁，不检查存储桶
+pub fn restore(app: &AppHandle) {
+    #[cfg(not(any(target_os = "ios", target_os = "android")))]
+    {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = async {
+                let Ok(data) = tokio::fs::read(settings_path(&app)?).await else {
+                    return Ok(());
+                };
+                let settings = serde_json::from_slice(&data)
+                    .map_err(|e| format!("剪贴板监听设置格式错误：{}", e))?;
+                watch(&app, settings).await
+            }
+            .await;
+            if let Err(e) = result {
+                let _ = app.emit("clipboard-watch-error", e);
+            }
+        });
+    }
+
+    #[cfg(any(target_os = "ios", target_os = "android"))]
+    let _ = app;
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("无法获取应用数据目录：{}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("无法创建应用数据目录：{}", e))?;
+    Ok(dir.join(SETTINGS_FILENAME))
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+async fn watch(app: &AppHandle, settings: ClipboardWatchSettings) -> Result<(), String> {
+    let mut watcher = WATCHER.lock().await;
+    if let Some(previous) = watcher.take() {
+        app.unlisten(previous.listener);
+    }
+
+    let clipboard = app.state::<Clipboard>();
+    if !clipboard.is_monitor_running() {
+        clipboard.start_monitor(app.clone())?;
+    }
+
+    let handle = app.clone();
+    let listener = app.listen_any(MONITOR_EVENT, move |_event| {
+        let app = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = on_change(&app).await {
+                let _ = app.emit("clipboard-watch-error", e);
+            }
+        });
+    });
+    *watcher = Some(Watcher {
+        settings,
+        listener,
+        last_content: None,
+    });
+    Ok(())
+}
+
+/// 停止监听。剪贴板插件的监听器可能也被前端使用，不会停止
+#[tauri::command]
+pub async fn clipboard_watch_stop(app: AppHandle) -> Result<(), String> {
+    #[cfg(not(any(target_os = "ios", target_os = "android")))]
+    {
+        if let Some(watcher) = WATCHER.lock().await.take() {
+            app.unlisten(watcher.listener);
+        }
+        match tokio::fs::remove_file(settings_path(&app)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("无法删除剪贴板监听设置：{}", e));
+            }
+            _ => {}
+        }
+    }
+
+    #[cfg(any(target_os = "ios", target_os = "android"))]
+    let _ = app;
+    Ok(())
+}
+
+/// 返回当前的设置，未开启时为空
+#[tauri::command]
+pub async fn clipboard_watch_status() -> Result<Option<ClipboardWatchSettings>, String> {
+    #[cfg(not(any(target_os = "ios", target_os = "android")))]
+    return Ok(WATCHER
+        .lock()
+        .await
+        .as_ref()
+        .map(|watcher| watcher.settings.clone()));
+
+    #[cfg(any(target_os = "ios", target_os = "android"))]
+    Ok(None)
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+async fn on_change(app: &AppHandle) -> Result<(), String> {
+    let Some(settings) = WATCHER
+        .lock()
+        .await
+        .as_ref()
+        .map(|watcher| watcher.settings.clone())
+    else {
+        return Ok(());
+    };
+
+    let (content, files) = {
+        let clipboard = app.state::<Clipboard>();
+        if clipboard.has_image()? {
+            let data = clipboard.read_image_binary()?;
+            let content = format!("image:{:x}", Sha256::digest(&data));
+            let filename = format!("clipboard-{}.png", Local::now().format("%Y%m%d-%H%M%S"));
+            (content, vec![new_file(UploadSource::Bytes(data), filename)])
+        } else if settings.include_files && clipboard.has_files()? {
+            let paths = clipboard.read_files()?;
+            let content = format!("files:{}", paths.join("\n"));
+            let files = paths
+                .into_iter()
+                .filter(|path| Path::new(path).is_file())
+                .map(|path| {
+                    let filename = Path::new(&path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    new_file(UploadSource::FilePath(path), filename)
+                })
+                .collect();
+            (content, files)
+        } else {
+            return Ok(());
+        }
+    };
+
+    // 在上传前更新，连续的变化事件不会重复上传同一内容
+    match WATCHER.lock().await.as_mut() {
+        Some(watcher) if watcher.last_content.as_ref() != Some(&content) => {
+            watcher.last_content = Some(content);
+        }
+        _ => return Ok(()),
+    }
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let profile = Arc::new(profile::get_profile(&settings.profile_id).await?);
+    let client = Arc::new(R2Client::from_profile(&profile).await?);
+    let uploads = files
+        .into_iter()
+        .map(|file| r2::spawn_upload(app, client.clone(), profile.clone(), file, None))
+        .collect::<Vec<_>>();
+    let mut links = Vec::new();
+    for upload in uploads {
+        // 失败的文件已通过 upload-progress 事件通知前端，取消的文件跳过
+        if let Ok(Ok(details)) = upload.await {
+            // 使用存储桶配置的默认链接格式
+            links.extend(details.links.into_iter().next().map(|link| link.text));
+        }
+    }
+    if !links.is_empty() {
+        app.state::<Clipboard>().write_text(links.join("\n"))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+fn new_file(source: UploadSource, remote_filename: String) -> File {
+    File {
+        id: Uuid::new_v4().to_string(),
+        source,
+        remote_filename,
+        key_template: None,
+        headers: ObjectHeaders::default(),
+    }
+}
@@ -0,0 +1,125 @@
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+// 未配置 MIME 列表时默认压缩的类型，支持 `text/*` 这样的通配
+const DEFAULT_MIME_TYPES: &[&str] = &[
+    "text/*",
+    "application/javascript",
+    "application/json",
+    "application/xml",
+    "application/wasm",
+    "application/manifest+json",
+    "image/svg+xml",
+];
+
+// 压缩需要将整个文件读入内存并单次上传，超过此大小的文件不压缩
+const MAX_COMPRESS_SIZE: u64 = 32 * 1024 * 1024;
+const DEFAULT_GZIP_LEVEL: u32 = 6;
+// brotli 11 级对较大的文件非常慢，默认使用速度与压缩率较均衡的 5 级
+const DEFAULT_BROTLI_QUALITY: u32 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Encoding {
+    #[default]
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    /// Content-Encoding 头的值
+    pub fn header_value(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    /// 同时上传原始文件时，压缩版本 key 的后缀
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => ".gz",
+            Encoding::Brotli => ".br",
+        }
+    }
+}
+
+/// 上传前压缩的规则，按存储桶配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompressionRules {
+    pub enabled: bool,
+    pub encoding: Encoding,
+    /// 压缩等级，gzip 为 0-9，brotli 为 0-11，为空时 gzip 使用 6，brotli 使用 5
+    pub level: Option<u32>,
+    /// 需要压缩的 MIME 类型，为空时使用内置列表
+    pub mime_types: Vec<String>,
+    /// 小于此大小的文件不压缩
+    pub min_size: u64,
+    /// 为 true 时保留原始文件，另外上传带 `.gz`/`.br` 后缀的压缩版本
+    pub upload_variants: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionPlan {
+    pub encoding: Encoding,
+    pub level: Option<u32>,
+    pub upload_variants: bool,
+}
+
+fn mime_matches(pattern: &str, content_type: &str) -> bool {
+    // 忽略 `; charset=utf-8` 之类的参数
+    let content_type = content_type.split(';').next().unwrap_or_default().trim();
+    match pattern.trim().strip_suffix("/*") {
+        Some(prefix) => content_type
+            .split_once('/')
+            .is_some_and(|(top, _)| top.eq_ignore_ascii_case(prefix)),
+        None => pattern.trim().eq_ignore_ascii_case(content_type),
+    }
+}
+
+impl CompressionRules {
+    /// 判断指定类型和大小的文件是否需要压缩
+    pub fn plan(&self, content_type: &str, size: u64) -> Option<CompressionPlan> {
+        if !self.enabled || size < self.min_size || size > MAX_COMPRESS_SIZE {
+            return None;
+        }
+
+        let matched = if self.mime_types.is_empty() {
+            DEFAULT_MIME_TYPES
+                .iter()
+                .any(|pattern| mime_matches(pattern, content_type))
+        } else {
+            self.mime_types
+                .iter()
+                .any(|pattern| mime_matches(pattern, content_type))
+        };
+
+        matched.then_some(CompressionPlan {
+            encoding: self.encoding,
+            level: self.level,
+            upload_variants: self.upload_variants,
+        })
+    }
+}
+
+pub fn compress(data: &[u8], encoding: Encoding, level: Option<u32>) -> Result<Vec<u8>, String> {
+    match encoding {
+        Encoding::Gzip => {
+            let level = flate2::Compression::new(level.unwrap_or(DEFAULT_GZIP_LEVEL).min(9));
+            let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 2), level);
+            encoder.write_all(data).map_err(|e| e.to_string())?;
+            encoder.finish().map_err(|e| e.to_string())
+        }
+        Encoding::Brotli => {
+            let mut out = Vec::with_capacity(data.len() / 2);
+            {
+                let quality = level.unwrap_or(DEFAULT_BROTLI_QUALITY).min(11);
+                let mut writer = brotli::CompressorWriter::new(&mut out, 4096, quality, 22);
+                writer.write_all(data).map_err(|e| e.to_string())?;
+            }
+            Ok(out)
+        }
+    }
+}
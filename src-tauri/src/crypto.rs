@@ -0,0 +1,65 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::{engine::general_purpose, Engine};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 12;
+pub const SALT_LEN: usize = 16;
+
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut buf = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut buf);
+    buf
+}
+
+// 使用 Argon2id 从口令或密钥文件内容派生 256 位密钥
+pub fn derive_key(secret: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|e| format!("派生密钥失败：{}", e))?;
+    Ok(key)
+}
+
+// 使用 HKDF-SHA256 从主密钥与 salt 派生 256 位子密钥（输出恰好一个块，只需一轮扩展）
+pub fn expand_key(master: &[u8; KEY_LEN], salt: &[u8], info: &[u8]) -> [u8; KEY_LEN] {
+    let hmac = |key: &[u8], parts: &[&[u8]]| {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes()
+    };
+    let prk = hmac(salt, &[master]);
+    hmac(&prk, &[info, &[1]]).into()
+}
+
+// 加密后输出 base64(nonce || ciphertext)
+pub fn seal(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<String, String> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = random_bytes::<NONCE_LEN>();
+    let mut out = nonce.to_vec();
+    out.extend(
+        cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|e| format!("加密失败：{}", e))?,
+    );
+    Ok(general_purpose::STANDARD.encode(out))
+}
+
+pub fn open(key: &[u8; KEY_LEN], sealed: &str) -> Result<Vec<u8>, String> {
+    let data = general_purpose::STANDARD
+        .decode(sealed)
+        .map_err(|e| format!("密文格式错误：{}", e))?;
+    if data.len() < NONCE_LEN {
+        return Err("密文格式错误".to_string());
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "解密失败，密钥不正确或数据已损坏".to_string())
+}
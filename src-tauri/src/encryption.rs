@@ -0,0 +1,406 @@
+use crate::crypto;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose, Engine};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+// 明文按 64KB 分段加密，每段附带 16 字节的认证标签
+pub const SEGMENT_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const NONCE_PREFIX_LEN: usize = 7;
+
+pub const SCHEME: &str = "aes-256-gcm-stream-v1";
+const KEY_INFO: &[u8] = b"r2u-enc-object-key";
+// 以下元数据键在上传时自动加上 `x-amz-meta-` 前缀
+pub const META_SCHEME: &str = "r2u-enc";
+// 主密钥的 Argon2id salt
+const META_SALT: &str = "r2u-enc-salt";
+// 对象密钥的 HKDF salt，每个对象不同
+const META_KEY_SALT: &str = "r2u-enc-key-salt";
+const META_NONCE: &str = "r2u-enc-nonce";
+const META_SEGMENT: &str = "r2u-enc-segment";
+const META_CONTENT_TYPE: &str = "r2u-enc-content-type";
+const META_SIZE: &str = "r2u-enc-size";
+
+/// 客户端加密配置，按存储桶配置。passphrase 与 key_file 二选一，passphrase 优先
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    pub passphrase: Option<String>,
+    pub key_file: Option<String>,
+    // 更新配置时 passphrase 为空表示保留原有的口令，设置此项才会清除
+    #[serde(skip_serializing)]
+    pub clear_passphrase: bool,
+}
+
+// 主密钥缓存，键是口令或密钥文件内容的 SHA-256。Argon2id 很慢，
+// 同一口令在应用运行期间只派生一次，各对象再通过 HKDF 派生独立的密钥
+static MASTER_KEYS: Lazy<DashMap<[u8; 32], Arc<OnceCell<MasterKey>>>> = Lazy::new(DashMap::new);
+
+#[derive(Clone, Copy)]
+struct MasterKey {
+    salt: [u8; crypto::SALT_LEN],
+    key: [u8; crypto::KEY_LEN],
+}
+
+async fn derive_master(secret: Vec<u8>, salt: Vec<u8>) -> Result<[u8; crypto::KEY_LEN], String> {
+    tokio::task::spawn_blocking(move || crypto::derive_key(&secret, &salt))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+impl EncryptionConfig {
+    // 加密使用的主密钥，并发上传的文件共享同一次派生
+    async fn master_key(&self) -> Result<MasterKey, String> {
+        let secret = self.secret().await?;
+        let digest: [u8; 32] = Sha256::digest(&secret).into();
+        let cell = MASTER_KEYS.entry(digest).or_default().clone();
+        let master = cell
+            .get_or_try_init(|| async move {
+                let salt = crypto::random_bytes::<{ crypto::SALT_LEN }>();
+                let key = derive_master(secret, salt.to_vec()).await?;
+                Ok::<_, String>(MasterKey { salt, key })
+            })
+            .await?;
+        Ok(*master)
+    }
+
+    // 解密时按对象的 salt 获取主密钥，与缓存的 salt 相同时不再派生
+    async fn master_key_for(&self, salt: Vec<u8>) -> Result<[u8; crypto::KEY_LEN], String> {
+        let secret = self.secret().await?;
+        let digest: [u8; 32] = Sha256::digest(&secret).into();
+        let cached = MASTER_KEYS
+            .get(&digest)
+            .and_then(|cell| cell.get().copied())
+            .filter(|master| master.salt[..] == salt[..]);
+        match cached {
+            Some(master) => Ok(master.key),
+            None => derive_master(secret, salt).await,
+        }
+    }
+
+    async fn secret(&self) -> Result<Vec<u8>, String> {
+        if let Some(passphrase) = self.passphrase.as_ref().filter(|p| !p.is_empty()) {
+            return Ok(passphrase.as_bytes().to_vec());
+        }
+        match self.key_file.as_ref().filter(|p| !p.is_empty()) {
+            Some(path) => tokio::fs::read(path)
+                .await
+                .map_err(|e| format!("无法读取密钥文件：{}", e)),
+            None => Err("未配置加密口令或密钥文件".to_string()),
+        }
+    }
+}
+
+// 分段 nonce：7 字节随机前缀 + 4 字节分段序号（大端）+ 1 字节末段标记，
+// 可防止分段被重排或截断
+fn segment_nonce(
+    prefix: &[u8; NONCE_PREFIX_LEN],
+    index: u32,
+    last: bool,
+) -> [u8; crypto::NONCE_LEN] {
+    let mut nonce = [0u8; crypto::NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..NONCE_PREFIX_LEN + 4].copy_from_slice(&index.to_be_bytes());
+    nonce[crypto::NONCE_LEN - 1] = last as u8;
+    nonce
+}
+
+fn cipher(key: &[u8; crypto::KEY_LEN]) -> Aes256Gcm {
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+}
+
+/// 单个对象的加密器，每个对象使用独立的密钥 salt 与 nonce 前缀
+#[derive(Clone)]
+pub struct ObjectCipher {
+    master: MasterKey,
+    key: [u8; crypto::KEY_LEN],
+    salt: [u8; crypto::SALT_LEN],
+    key_salt: [u8; crypto::SALT_LEN],
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+}
+
+impl std::fmt::Debug for ObjectCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectCipher").finish_non_exhaustive()
+    }
+}
+
+impl ObjectCipher {
+    pub async fn new(config: &EncryptionConfig) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        Ok(Some(Self::from_master(config.master_key().await?)))
+    }
+
+    fn from_master(master: MasterKey) -> Self {
+        let key_salt = crypto::random_bytes::<{ crypto::SALT_LEN }>();
+        Self {
+            master,
+            key: crypto::expand_key(&master.key, &key_salt, KEY_INFO),
+            salt: master.salt,
+            key_salt,
+            nonce_prefix: crypto::random_bytes::<NONCE_PREFIX_LEN>(),
+        }
+    }
+
+    /// 使用同一主密钥生成新的对象密钥与 nonce 前缀。重新上传时文件内容可能已经变化，
+    /// 不能用相同的 nonce 再次加密
+    pub fn renew(&self) -> Self {
+        Self::from_master(self.master)
+    }
+
+    /// 与对象密钥相关的元数据，renew 后需要重新写入
+    pub fn key_metadata(&self) -> HashMap<String, String> {
+        HashMap::from([
+            (
+                META_SALT.to_string(),
+                general_purpose::STANDARD.encode(self.salt),
+            ),
+            (
+                META_KEY_SALT.to_string(),
+                general_purpose::STANDARD.encode(self.key_salt),
+            ),
+            (
+                META_NONCE.to_string(),
+                general_purpose::STANDARD.encode(self.nonce_prefix),
+            ),
+        ])
+    }
+
+    /// 解密时需要的元数据，原始 Content-Type 与大小也保存在其中
+    pub fn metadata(&self, content_type: &str, plaintext_size: u64) -> HashMap<String, String> {
+        let mut metadata = self.key_metadata();
+        metadata.extend([
+            (META_SCHEME.to_string(), SCHEME.to_string()),
+            (META_SEGMENT.to_string(), SEGMENT_LEN.to_string()),
+            (META_CONTENT_TYPE.to_string(), content_type.to_string()),
+            (META_SIZE.to_string(), plaintext_size.to_string()),
+        ]);
+        metadata
+    }
+
+    /// 加密一段连续的明文。first_segment 为这段明文第一个分段的序号，
+    /// is_last 表示这段明文是否为对象的结尾。除结尾外，明文长度必须是 SEGMENT_LEN 的整数倍
+    pub fn encrypt(
+        &self,
+        data: &[u8],
+        first_segment: u32,
+        is_last: bool,
+    ) -> Result<Vec<u8>, String> {
+        let cipher = cipher(&self.key);
+        let segments = data.len().div_ceil(SEGMENT_LEN).max(1);
+        let mut out = Vec::with_capacity(data.len() + segments * TAG_LEN);
+
+        for i in 0..segments {
+            let start = i * SEGMENT_LEN;
+            let end = (start + SEGMENT_LEN).min(data.len());
+            let last = is_last && i == segments - 1;
+            let nonce = segment_nonce(&self.nonce_prefix, first_segment + i as u32, last);
+            out.extend(
+                cipher
+                    .encrypt(Nonce::from_slice(&nonce), &data[start..end])
+                    .map_err(|e| format!("加密失败：{}", e))?,
+            );
+        }
+        Ok(out)
+    }
+}
+
+/// 流式解密器，按顺序输入密文，输出明文
+pub struct ObjectDecryptor {
+    key: [u8; crypto::KEY_LEN],
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    next_segment: u32,
+    buffer: Vec<u8>,
+}
+
+impl ObjectDecryptor {
+    /// 根据对象元数据创建解密器，对象未加密时返回 None
+    pub async fn from_metadata(
+        config: &EncryptionConfig,
+        metadata: &HashMap<String, String>,
+    ) -> Result<Option<Self>, String> {
+        match metadata.get(META_SCHEME).map(String::as_str) {
+            None => return Ok(None),
+            Some(SCHEME) => {}
+            Some(other) => return Err(format!("不支持的加密格式：{}", other)),
+        }
+        // 分段长度来自对象元数据，只接受固定值，避免超大的值导致溢出或缓冲整个对象
+        if metadata.get(META_SEGMENT).map(String::as_str) != Some(&SEGMENT_LEN.to_string()) {
+            return Err(format!("加密元数据缺失或损坏：{}", META_SEGMENT));
+        }
+
+        let decode = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|v| general_purpose::STANDARD.decode(v).ok())
+                .ok_or_else(|| format!("加密元数据缺失或损坏：{}", key))
+        };
+        let salt = decode(META_SALT)?;
+        let nonce_prefix: [u8; NONCE_PREFIX_LEN] = decode(META_NONCE)?
+            .try_into()
+            .map_err(|_| format!("加密元数据缺失或损坏：{}", META_NONCE))?;
+        let key_salt = decode(META_KEY_SALT)?;
+        let master = config.master_key_for(salt).await?;
+        let key = crypto::expand_key(&master, &key_salt, KEY_INFO);
+
+        Ok(Some(Self {
+            key,
+            nonce_prefix,
+            next_segment: 0,
+            buffer: Vec::new(),
+        }))
+    }
+
+    fn decrypt_segment(&mut self, data: &[u8], last: bool) -> Result<Vec<u8>, String> {
+        let nonce = segment_nonce(&self.nonce_prefix, self.next_segment, last);
+        self.next_segment += 1;
+        cipher(&self.key)
+            .decrypt(Nonce::from_slice(&nonce), data)
+            .map_err(|_| "解密失败，密钥不正确或数据已损坏".to_string())
+    }
+
+    /// 输入一段密文，返回可以确定不是末段的已解密明文
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        self.buffer.extend_from_slice(data);
+        let encrypted_segment = SEGMENT_LEN + TAG_LEN;
+        let mut out = Vec::new();
+        // 至少保留一个分段，直到 finish 时才能确定哪一段是末段
+        while self.buffer.len() > encrypted_segment {
+            let segment: Vec<u8> = self.buffer.drain(..encrypted_segment).collect();
+            out.extend(self.decrypt_segment(&segment, false)?);
+        }
+        Ok(out)
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, String> {
+        let segment = std::mem::take(&mut self.buffer);
+        self.decrypt_segment(&segment, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(passphrase: &str) -> EncryptionConfig {
+        EncryptionConfig {
+            enabled: true,
+            passphrase: Some(passphrase.to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn decrypt(
+        config: &EncryptionConfig,
+        metadata: &HashMap<String, String>,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let mut decryptor = ObjectDecryptor::from_metadata(config, metadata)
+            .await?
+            .unwrap();
+        // 按不对齐分段的大小输入，模拟网络分块
+        let mut out = Vec::new();
+        for chunk in ciphertext.chunks(1000) {
+            out.extend(decryptor.update(chunk)?);
+        }
+        out.extend(decryptor.finish()?);
+        Ok(out)
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let config = config("round trip");
+        let data: Vec<u8> = (0..SEGMENT_LEN * 2 + 123).map(|i| i as u8).collect();
+        let cipher = ObjectCipher::new(&config).await.unwrap().unwrap();
+        let metadata = cipher.metadata("image/png", data.len() as u64);
+
+        // 分两次加密，与分块上传一致
+        let mut ciphertext = cipher.encrypt(&data[..SEGMENT_LEN], 0, false).unwrap();
+        ciphertext.extend(cipher.encrypt(&data[SEGMENT_LEN..], 1, true).unwrap());
+        assert_eq!(ciphertext.len(), data.len() + 3 * TAG_LEN);
+        assert_eq!(
+            decrypt(&config, &metadata, &ciphertext).await.unwrap(),
+            data
+        );
+
+        // 空对象也有一个带认证标签的末段
+        let ciphertext = cipher.encrypt(&[], 0, true).unwrap();
+        assert_eq!(decrypt(&config, &metadata, &ciphertext).await.unwrap(), b"");
+    }
+
+    #[tokio::test]
+    async fn objects_use_different_keys() {
+        let config = config("different keys");
+        let a = ObjectCipher::new(&config).await.unwrap().unwrap();
+        let b = ObjectCipher::new(&config).await.unwrap().unwrap();
+        // 主密钥只派生一次，对象密钥各不相同
+        assert_eq!(a.salt, b.salt);
+        assert_ne!(a.key_salt, b.key_salt);
+        assert_ne!(a.key, b.key);
+
+        let ciphertext = a.encrypt(b"secret", 0, true).unwrap();
+        let metadata = b.metadata("text/plain", 6);
+        assert!(decrypt(&config, &metadata, &ciphertext).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_truncation_and_wrong_passphrase() {
+        let config = config("final segment");
+        let data = vec![7u8; SEGMENT_LEN * 2];
+        let cipher = ObjectCipher::new(&config).await.unwrap().unwrap();
+        let metadata = cipher.metadata("application/octet-stream", data.len() as u64);
+        let ciphertext = cipher.encrypt(&data, 0, true).unwrap();
+        assert_eq!(
+            decrypt(&config, &metadata, &ciphertext).await.unwrap(),
+            data
+        );
+
+        // 去掉末段后，剩下的最后一段没有末段标记，不能通过认证
+        let truncated = &ciphertext[..SEGMENT_LEN + TAG_LEN];
+        assert!(decrypt(&config, &metadata, truncated).await.is_err());
+
+        // 没有标记末段的密文同样无法解密
+        let unfinished = cipher.encrypt(&data, 0, false).unwrap();
+        assert!(decrypt(&config, &metadata, &unfinished).await.is_err());
+
+        let wrong = self::config("wrong passphrase");
+        assert!(decrypt(&wrong, &metadata, &ciphertext).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn unencrypted_objects() {
+        let config = config("plain");
+        let metadata = HashMap::new();
+        assert!(ObjectDecryptor::from_metadata(&config, &metadata)
+            .await
+            .unwrap()
+            .is_none());
+        let metadata = HashMap::from([(META_SCHEME.to_string(), "unknown".to_string())]);
+        assert!(ObjectDecryptor::from_metadata(&config, &metadata)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_other_segment_lengths() {
+        let config = config("segment length");
+        let cipher = ObjectCipher::new(&config).await.unwrap().unwrap();
+        let mut metadata = cipher.metadata("text/plain", 0);
+        for len in ["1024", &usize::MAX.to_string(), "abc"] {
+            metadata.insert(META_SEGMENT.to_string(), len.to_string());
+            assert!(ObjectDecryptor::from_metadata(&config, &metadata)
+                .await
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,513 @@
+use crate::typ::UploadDetails;
+use once_cell::sync::OnceCell;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const DB_FILENAME: &str = "history.db";
+const SCHEMA_VERSION: i32 = 1;
+const DEFAULT_LIMIT: u32 = 100;
+
+static DB: OnceCell<Mutex<Connection>> = OnceCell::new();
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS uploads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_id TEXT NOT NULL,
+    profile_id TEXT NOT NULL,
+    bucket TEXT NOT NULL,
+    key TEXT NOT NULL,
+    url TEXT NOT NULL DEFAULT '',
+    local_path TEXT,
+    size INTEGER,
+    content_type TEXT,
+    sha256 TEXT,
+    duration_ms INTEGER,
+    status TEXT NOT NULL,
+    error TEXT,
+    details TEXT,
+    created_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    batch_id TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_uploads_created_at ON uploads (created_at);
+CREATE INDEX IF NOT EXISTS idx_uploads_file_id ON uploads (file_id);
+CREATE INDEX IF NOT EXISTS idx_uploads_batch_id ON uploads (batch_id);
+";
+
+const COLUMNS: &str = "id, file_id, profile_id, bucket, key, url, local_path, size, content_type, \
+     sha256, duration_ms, status, error, details, created_at, finished_at, batch_id";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryStatus {
+    // 上传中，应用退出时仍为该状态的记录表示上传被中断
+    Uploading,
+    Success,
+    Error,
+    Cancelled,
+}
+
+impl HistoryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryStatus::Uploading => "uploading",
+            HistoryStatus::Success => "success",
+            HistoryStatus::Error => "error",
+            HistoryStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "success" => HistoryStatus::Success,
+            "error" => HistoryStatus::Error,
+            "cancelled" => HistoryStatus::Cancelled,
+            _ => HistoryStatus::Uploading,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    pub id: i64,
+    pub file_id: String,
+    pub profile_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub url: String,
+    pub local_path: Option<String>,
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    // 上传内容（压缩、加密前）的 SHA-256
+    pub sha256: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub status: HistoryStatus,
+    pub error: Option<String>,
+    pub details: Option<UploadDetails>,
+    // Unix 时间戳（秒）
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    // 通过 r2_upload 上传时所属的批次
+    pub batch_id: Option<String>,
+}
+
+/// 开始上传时写入的信息
+pub struct UploadStarted {
+    pub file_id: String,
+    pub profile_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub local_path: Option<String>,
+    pub batch_id: Option<String>,
+}
+
+/// 上传结束时更新的信息
+pub struct UploadFinished {
+    pub key: String,
+    pub url: String,
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    pub sha256: Option<String>,
+    pub duration_ms: u64,
+    pub status: HistoryStatus,
+    pub error: Option<String>,
+    pub details: Option<UploadDetails>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryQuery {
+    pub profile_id: Option<String>,
+    pub status: Option<HistoryStatus>,
+    // 在 key、URL 与本地路径中模糊搜索
+    pub search: Option<String>,
+    // Unix 时间戳（秒），包含边界
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub limit: Option<u32>,
+    pub offset: u32,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(DB_FILENAME))
+        .map_err(|e| format!("无法获取应用数据目录：{}", e))
+}
+
+fn open(path: &Path) -> Result<Connection, String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("无法创建应用数据目录：{}", e))?;
+    }
+    let conn = Connection::open(path).map_err(|e| format!("无法打开上传历史数据库：{}", e))?;
+    let migrate = || -> rusqlite::Result<()> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version < SCHEMA_VERSION {
+            conn.execute_batch(SCHEMA)?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        Ok(())
+    };
+    migrate().map_err(|e| format!("无法初始化上传历史数据库：{}", e))?;
+    Ok(conn)
+}
+
+// 数据库操作在阻塞线程中进行，所有操作共用一个连接
+async fn with_db<T, F>(app: &AppHandle, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+{
+    let path = db_path(app)?;
+    tokio::task::spawn_blocking(move || {
+        let db = DB.get_or_try_init(|| open(&path).map(Mutex::new))?;
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        f(&conn).map_err(|e| format!("上传历史数据库错误：{}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn insert_started(
+    conn: &Connection,
+    upload: &UploadStarted,
+    created_at: i64,
+) -> rusqlite::Result<i64> {
+    conn.prepare_cached(
+        "INSERT INTO uploads (file_id, profile_id, bucket, key, local_path, status, created_at,
+             batch_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    )?
+    .execute(params![
+        upload.file_id,
+        upload.profile_id,
+        upload.bucket,
+        upload.key,
+        upload.local_path,
+        HistoryStatus::Uploading.as_str(),
+        created_at,
+        upload.batch_id,
+    ])?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// 记录开始上传，返回记录 id
+pub async fn record_start(app: &AppHandle, upload: UploadStarted) -> Result<i64, String> {
+    with_db(app, move |conn| insert_started(conn, &upload, now() as i64)).await
+}
+
+/// 在同一个事务中记录多个文件开始上传，按顺序返回记录 id
+pub async fn record_start_many(
+    app: &AppHandle,
+    uploads: Vec<UploadStarted>,
+) -> Result<Vec<i64>, String> {
+    with_db(app, move |conn| {
+        let tx = conn.unchecked_transaction()?;
+        let created_at = now() as i64;
+        let ids = uploads
+            .iter()
+            .map(|upload| insert_started(&tx, upload, created_at))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        tx.commit()?;
+        Ok(ids)
+    })
+    .await
+}
+
+pub async fn record_finish(app: &AppHandle, id: i64, upload: UploadFinished) -> Result<(), String> {
+    let details = upload
+        .details
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    with_db(app, move |conn| {
+        conn.execute(
+            "UPDATE uploads SET key = ?2, url = ?3, size = ?4, content_type = ?5, sha256 = ?6,
+                 duration_ms = ?7, status = ?8, error = ?9, details = ?10, finished_at = ?11
+             WHERE id = ?1",
+            params![
+                id,
+                upload.key,
+                upload.url,
+                upload.size.map(|size| size as i64),
+                upload.content_type,
+                upload.sha256,
+                upload.duration_ms as i64,
+                upload.status.as_str(),
+                upload.error,
+                details,
+                now() as i64,
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// 取消上传时任务已被终止，只能按 file_id 更新仍在上传中的记录
+pub async fn record_cancelled(app: &AppHandle, file_id: &str) -> Result<(), String> {
+    let file_id = file_id.to_string();
+    with_db(app, move |conn| {
+        conn.execute(
+            "UPDATE uploads SET status = ?2, finished_at = ?3 WHERE file_id = ?1 AND status = ?4",
+            params![
+                file_id,
+                HistoryStatus::Cancelled.as_str(),
+                now() as i64,
+                HistoryStatus::Uploading.as_str(),
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+fn read_record(row: &Row) -> rusqlite::Result<HistoryRecord> {
+    let details: Option<String> = row.get("details")?;
+    let status: String = row.get("status")?;
+    Ok(HistoryRecord {
+        id: row.get("id")?,
+        file_id: row.get("file_id")?,
+        profile_id: row.get("profile_id")?,
+        bucket: row.get("bucket")?,
+        key: row.get("key")?,
+        url: row.get("url")?,
+        local_path: row.get("local_path")?,
+        size: row.get::<_, Option<i64>>("size")?.map(|size| size as u64),
+        content_type: row.get("content_type")?,
+        sha256: row.get("sha256")?,
+        duration_ms: row
+            .get::<_, Option<i64>>("duration_ms")?
+            .map(|ms| ms as u64),
+        status: HistoryStatus::parse(&status),
+        error: row.get("error")?,
+        // 格式不兼容的旧记录忽略详细信息
+        details: details.and_then(|details| serde_json::from_str(&details).ok()),
+        created_at: row.get::<_, i64>("created_at")? as u64,
+        finished_at: row.get::<_, Option<i64>>("finished_at")?.map(|t| t as u64),
+        batch_id: row.get("batch_id")?,
+    })
+}
+
+// LIKE 中的 `%`、`_` 按字面匹配
+fn like_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+fn query_records(
+    conn: &Connection,
+    query: &HistoryQuery,
+    default_limit: Option<u32>,
+) -> rusqlite::Result<Vec<HistoryRecord>> {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+    if let Some(profile_id) = &query.profile_id {
+        conditions.push("profile_id = ?");
+        values.push(profile_id.clone().into());
+    }
+    if let Some(status) = query.status {
+        conditions.push("status = ?");
+        values.push(status.as_str().to_string().into());
+    }
+    if let Some(search) = query.search.as_deref().filter(|s| !s.trim().is_empty()) {
+        conditions.push(
+            "(key LIKE ? ESCAPE '\\' OR url LIKE ? ESCAPE '\\' OR local_path LIKE ? ESCAPE '\\')",
+        );
+        let pattern = like_pattern(search.trim());
+        values.extend(std::iter::repeat_n(Value::from(pattern), 3));
+    }
+    if let Some(since) = query.since {
+        conditions.push("created_at >= ?");
+        values.push((since as i64).into());
+    }
+    if let Some(until) = query.until {
+        conditions.push("created_at <= ?");
+        values.push((until as i64).into());
+    }
+
+    let mut sql = format!("SELECT {} FROM uploads", COLUMNS);
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+    }
+    sql.push_str(" ORDER BY created_at DESC, id DESC");
+    // SQLite 中 LIMIT -1 表示不限制
+    let limit = query.limit.or(default_limit).map_or(-1, i64::from);
+    sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, query.offset));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let records = stmt
+        .query_map(params_from_iter(values), read_record)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(records)
+}
+
+/// 按条件查询上传历史，按时间倒序，默认最多返回 100 条
+#[tauri::command]
+pub async fn history_list(
+    app: AppHandle,
+    query: Option<HistoryQuery>,
+) -> Result<Vec<HistoryRecord>, String> {
+    let query = query.unwrap_or_default();
+    with_db(&app, move |conn| {
+        query_records(conn, &query, Some(DEFAULT_LIMIT))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn history_get(app: AppHandle, id: i64) -> Result<Option<HistoryRecord>, String> {
+    with_db(&app, move |conn| {
+        conn.query_row(
+            &format!("SELECT {} FROM uploads WHERE id = ?1", COLUMNS),
+            params![id],
+            read_record,
+        )
+        .optional()
+    })
+    .await
+}
+
+/// 批次中的所有记录，按提交顺序
+pub async fn batch_records(app: &AppHandle, batch_id: &str) -> Result<Vec<HistoryRecord>, String> {
+    let batch_id = batch_id.to_string();
+    with_db(app, move |conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM uploads WHERE batch_id = ?1 ORDER BY id",
+            COLUMNS
+        ))?;
+        let records = stmt
+            .query_map(params![batch_id], read_record)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(records)
+    })
+    .await
+}
+
+/// 删除指定的记录，ids 为空时清空全部历史
+#[tauri::command]
+pub async fn history_delete(app: AppHandle, ids: Vec<i64>) -> Result<usize, String> {
+    with_db(&app, move |conn| {
+        if ids.is_empty() {
+            return conn.execute("DELETE FROM uploads", []);
+        }
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        conn.execute(
+            &format!("DELETE FROM uploads WHERE id IN ({})", placeholders),
+            params_from_iter(ids),
+        )
+    })
+    .await
+}
+
+/// 按条件导出上传历史到文件，导出时默认不限制条数
+#[tauri::command]
+pub async fn history_export(
+    app: AppHandle,
+    path: String,
+    format: ExportFormat,
+    query: Option<HistoryQuery>,
+) -> Result<usize, String> {
+    let query = query.unwrap_or_default();
+    let records = with_db(&app, move |conn| query_records(conn, &query, None)).await?;
+    let content = match format {
+        ExportFormat::Json => serde_json::to_vec_pretty(&records).map_err(|e| e.to_string())?,
+        ExportFormat::Csv => records_to_csv(&records).into_bytes(),
+    };
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("无法写入导出文件：{}", e))?;
+    Ok(records.len())
+}
+
+fn records_to_csv(records: &[HistoryRecord]) -> String {
+    let mut out = csv_row(&[
+        "id",
+        "created_at",
+        "status",
+        "bucket",
+        "key",
+        "url",
+        "local_path",
+        "size",
+        "content_type",
+        "sha256",
+        "duration_ms",
+        "error",
+    ]);
+    for record in records {
+        out.push_str(&csv_row(&[
+            &record.id.to_string(),
+            &record.created_at.to_string(),
+            record.status.as_str(),
+            &record.bucket,
+            &record.key,
+            &record.url,
+            record.local_path.as_deref().unwrap_or_default(),
+            &record.size.map(|s| s.to_string()).unwrap_or_default(),
+            record.content_type.as_deref().unwrap_or_default(),
+            record.sha256.as_deref().unwrap_or_default(),
+            &record
+                .duration_ms
+                .map(|ms| ms.to_string())
+                .unwrap_or_default(),
+            record.error.as_deref().unwrap_or_default(),
+        ]));
+    }
+    out
+}
+
+/// 生成一行 CSV（RFC 4180），包含逗号、引号或换行的字段加引号
+pub fn csv_row(fields: &[&str]) -> String {
+    let fields = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    format!("{}\r\n", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_row_quotes_fields() {
+        assert_eq!(csv_row(&["a", "", "b c"]), "a,,b c\r\n");
+        assert_eq!(
+            csv_row(&["a,b", "say \"hi\"", "line\nbreak", "cr\r"]),
+            "\"a,b\",\"say \"\"hi\"\"\",\"line\nbreak\",\"cr\r\"\r\n"
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+const SIGNATURE_HEADER: &str = "X-R2Uploader-Signature";
+const EVENT_HEADER: &str = "X-R2Uploader-Event";
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+// 第 n 次重试前等待 RETRY_DELAY * 2^(n-1)
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+// 同时执行回调的上传数，批量上传时其余的排队等待
+const MAX_CONCURRENT_HOOKS: usize = 4;
+
+static HOOK_PERMITS: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(MAX_CONCURRENT_HOOKS));
+// webhook 共用的 HTTP 客户端及创建时使用的系统代理，代理变化时重新创建
+type CachedClient = (Option<String>, reqwest::Client);
+static CLIENT: Lazy<Mutex<Option<CachedClient>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HookEvent {
+    Success,
+    Failure,
+}
+
+impl HookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::Success => "success",
+            HookEvent::Failure => "failure",
+        }
+    }
+}
+
+fn all_events() -> Vec<HookEvent> {
+    vec![HookEvent::Success, HookEvent::Failure]
+}
+
+/// 上传完成后以 POST 发送 JSON 的 HTTP 回调
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub url: String,
+    // HMAC-SHA256 签名密钥，与存储桶密钥一样加密保存
+    #[serde(default)]
+    pub secret: Option<String>,
+    // 更新配置时 secret 为空表示保留原有的密钥，设置此项才会清除
+    #[serde(default, skip_serializing)]
+    pub clear_secret: bool,
+    #[serde(default = "all_events")]
+    pub events: Vec<HookEvent>,
+    // 附加的请求头，如 Authorization
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // 网络错误、429 与 5xx 时的重试次数，为空时使用默认值
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+}
+
+/// 上传完成后执行的本地命令，通过环境变量与 stdin 接收上传信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandHook {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    #[serde(default = "all_events")]
+    pub events: Vec<HookEvent>,
+    // 超时后结束进程，为空时使用默认值
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// 按存储桶配置的上传完成回调
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HookSettings {
+    pub webhooks: Vec<Webhook>,
+    pub commands: Vec<CommandHook>,
+}
+
+impl HookSettings {
+    pub fn is_empty(&self) -> bool {
+        self.webhooks.is_empty() && self.commands.is_empty()
+    }
+}
+
+/// 发送给 webhook 与命令的上传信息
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HookPayload {
+    pub event: HookEvent,
+    pub file_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub url: String,
+    pub local_path: Option<String>,
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    pub sha256: Option<String>,
+    // 对象的自定义元数据
+    pub metadata: HashMap<String, String>,
+    pub error: Option<String>,
+    // Unix 时间戳（秒）
+    pub timestamp: u64,
+}
+
+/// 回调失败时通过 upload-hook-error 事件发送
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HookError {
+    pub file_id: String,
+    pub key: String,
+    pub errors: Vec<String>,
+}
+
+/// 依次执行所有订阅了该事件的回调。单个回调失败不影响其他回调，返回所有错误。
+/// 最多 MAX_CONCURRENT_HOOKS 个上传的回调同时执行
+pub async fn run(settings: &HookSettings, payload: &HookPayload) -> Vec<String> {
+    let _permit = match HOOK_PERMITS.acquire().await {
+        Ok(permit) => permit,
+        Err(e) => return vec![e.to_string()],
+    };
+    let body = match serde_json::to_vec(payload) {
+        Ok(body) => body,
+        Err(e) => return vec![e.to_string()],
+    };
+    let mut errors = Vec::new();
+    for webhook in &settings.webhooks {
+        if !webhook.events.contains(&payload.event) {
+            continue;
+        }
+        if let Err(e) = send_webhook(webhook, payload.event, &body).await {
+            errors.push(format!("webhook {} 失败：{}", webhook.url, e));
+        }
+    }
+    for hook in &settings.commands {
+        if !hook.events.contains(&payload.event) {
+            continue;
+        }
+        if let Err(e) = run_command(hook, payload, &body).await {
+            errors.push(format!("命令 {} 失败：{}", hook.program, e));
+        }
+    }
+    errors
+}
+
+/// 计算请求体的签名，格式为 `sha256=<hex>`，接收方用相同的密钥校验
+pub fn sign(secret: &str, body: &[u8]) -> Result<String, String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|e| e.to_string())?;
+    mac.update(body);
+    Ok(format!("sha256={:x}", mac.finalize().into_bytes()))
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    let proxy = crate::r2::system_proxy_uri();
+    let mut cached = CLIENT.lock().map_err(|e| e.to_string())?;
+    if let Some((cached_proxy, client)) = cached.as_ref() {
+        if *cached_proxy == proxy {
+            return Ok(client.clone());
+        }
+    }
+
+    let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+    if let Some(proxy) = &proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(|e| e.to_string())?);
+    }
+    let client = builder.build().map_err(|e| e.to_string())?;
+    *cached = Some((proxy, client.clone()));
+    Ok(client)
+}
+
+async fn send_webhook(webhook: &Webhook, event: HookEvent, body: &[u8]) -> Result<(), String> {
+    let client = client()?;
+    let signature = match webhook
+        .secret
+        .as_deref()
+        .filter(|secret| !secret.is_empty())
+    {
+        Some(secret) => Some(sign(secret, body)?),
+        None => None,
+    };
+
+    let max_retries = webhook.max_retries.unwrap_or(DEFAULT_RETRIES);
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event.name())
+            .body(body.to_vec());
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let (error, retryable) = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let retryable = status.is_server_error() || status.as_u16() == 429;
+                (format!("服务器返回 {}", status), retryable)
+            }
+            // 请求构建失败（如请求头不合法）时重试没有意义
+            Err(e) => (e.to_string(), !e.is_builder()),
+        };
+        if !retryable || attempt >= max_retries {
+            return Err(error);
+        }
+        tokio::time::sleep(RETRY_DELAY * 2u32.pow(attempt.min(6))).await;
+        attempt += 1;
+    }
+}
+
+// 环境变量以 R2_ 开头，stdin 为与 webhook 相同的 JSON
+fn command_env(payload: &HookPayload) -> Vec<(&'static str, String)> {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    vec![
+        ("R2_EVENT", payload.event.name().to_string()),
+        ("R2_FILE_ID", payload.file_id.clone()),
+        ("R2_BUCKET", payload.bucket.clone()),
+        ("R2_KEY", payload.key.clone()),
+        ("R2_URL", payload.url.clone()),
+        ("R2_LOCAL_PATH", optional(&payload.local_path)),
+        (
+            "R2_SIZE",
+            payload
+                .size
+                .map(|size| size.to_string())
+                .unwrap_or_default(),
+        ),
+        ("R2_CONTENT_TYPE", optional(&payload.content_type)),
+        ("R2_SHA256", optional(&payload.sha256)),
+        ("R2_ERROR", optional(&payload.error)),
+    ]
+}
+
+async fn run_command(hook: &CommandHook, payload: &HookPayload, body: &[u8]) -> Result<(), String> {
+    let mut command = Command::new(&hook.program);
+    command
+        .args(&hook.args)
+        .envs(command_env(payload))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = &hook.working_dir {
+        command.current_dir(dir);
+    }
+    let mut child = command.spawn().map_err(|e| format!("无法启动：{}", e))?;
+    let stdin = child.stdin.take();
+    let finished = async move {
+        if let Some(mut stdin) = stdin {
+            // 命令不读取 stdin 时写入会失败，忽略
+            let _ = stdin.write_all(body).await;
+        }
+        child.wait_with_output().await
+    };
+
+    let timeout = Duration::from_secs(hook.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let output = tokio::time::timeout(timeout, finished)
+        .await
+        .map_err(|_| format!("超过 {} 秒未结束", timeout.as_secs()))?
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(format!("{}：{}", output.status, stderr.trim()))
+}
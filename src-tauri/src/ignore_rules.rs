@@ -0,0 +1,135 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// 扫描目录时读取的忽略文件，语法与 .gitignore 相同
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".r2ignore"];
+
+const DEFAULT_EXCLUDES: &[&str] = &[
+    ".git/",
+    ".svn/",
+    ".hg/",
+    "node_modules/",
+    ".DS_Store",
+    "Thumbs.db",
+    "desktop.ini",
+    "*.tmp",
+    "*.swp",
+    "~$*",
+];
+
+/// 按存储桶配置的默认排除规则，语法与 .gitignore 相同
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanRules {
+    pub exclude: Vec<String>,
+}
+
+impl Default for ScanRules {
+    fn default() -> Self {
+        Self {
+            exclude: DEFAULT_EXCLUDES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// 某个目录及其所有上级目录中的忽略文件，内层目录的规则优先
+pub struct IgnoreChain {
+    matcher: Gitignore,
+    parent: Option<Arc<IgnoreChain>>,
+}
+
+pub struct IgnoreMatcher {
+    exclude: Gitignore,
+    include: Option<Override>,
+    respect_ignore_files: bool,
+}
+
+impl IgnoreMatcher {
+    /// include 为空时包含所有文件，只对文件生效；exclude 对文件与目录都生效
+    pub fn new(
+        root: &Path,
+        include: &[String],
+        exclude: &[String],
+        respect_ignore_files: bool,
+    ) -> Result<Self, String> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in exclude.iter().filter(|p| !p.trim().is_empty()) {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| format!("排除规则 {} 无效：{}", pattern, e))?;
+        }
+        let exclude = builder.build().map_err(|e| e.to_string())?;
+
+        let include = if include.iter().all(|p| p.trim().is_empty()) {
+            None
+        } else {
+            let mut builder = OverrideBuilder::new(root);
+            for pattern in include.iter().filter(|p| !p.trim().is_empty()) {
+                builder
+                    .add(pattern)
+                    .map_err(|e| format!("包含规则 {} 无效：{}", pattern, e))?;
+            }
+            Some(builder.build().map_err(|e| e.to_string())?)
+        };
+
+        Ok(Self {
+            exclude,
+            include,
+            respect_ignore_files,
+        })
+    }
+
+    /// 读取目录中的忽略文件，返回适用于该目录下条目的规则链
+    pub fn enter_dir(
+        &self,
+        dir: &Path,
+        parent: Option<Arc<IgnoreChain>>,
+    ) -> Option<Arc<IgnoreChain>> {
+        if !self.respect_ignore_files {
+            return parent;
+        }
+        let files = IGNORE_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return parent;
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        for file in &files {
+            // 忽略文件中个别无效的行不影响其他规则
+            let _ = builder.add(file);
+        }
+        match builder.build() {
+            Ok(matcher) => Some(Arc::new(IgnoreChain { matcher, parent })),
+            Err(_) => parent,
+        }
+    }
+
+    /// 用户与存储桶的排除规则优先，忽略文件中的 `!` 规则不能重新包含被它们排除的条目。
+    /// 未被排除时再按忽略文件的规则链判断，最后检查 include
+    pub fn is_ignored(&self, path: &Path, is_dir: bool, chain: Option<&Arc<IgnoreChain>>) -> bool {
+        if self.exclude.matched(path, is_dir).is_ignore() {
+            return true;
+        }
+
+        let mut current = chain;
+        while let Some(node) = current {
+            let matched = node.matcher.matched(path, is_dir);
+            if !matched.is_none() {
+                return matched.is_ignore();
+            }
+            current = node.parent.as_ref();
+        }
+
+        match &self.include {
+            Some(include) if !is_dir => !include.matched(path, false).is_whitelist(),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+const DEFAULT_QUALITY: u8 = 85;
+// AVIF 编码速度，1-10，越大越快
+const AVIF_SPEED: u8 = 8;
+
+/// 超过该大小的图片不进行优化和生成变体，直接上传原文件
+pub const MAX_SIZE: u64 = 64 * 1024 * 1024;
+/// 超过该像素数的图片不解码，约为 10000x10000
+pub const MAX_PIXELS: u64 = 100_000_000;
+// 解码时最多分配的内存，按 MAX_PIXELS 的 8 位 RGBA 图片计算
+const MAX_ALLOC: u64 = MAX_PIXELS * 4;
+
+/// 输出格式。image 库仅支持无损 WebP 编码，无法按质量压缩，因此不提供转换为 WebP
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputFormat {
+    /// 保持原格式
+    #[default]
+    Original,
+    Jpeg,
+    Png,
+    Avif,
+}
+
+/// 上传前的图片优化配置，按存储桶配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImageOptimization {
+    pub enabled: bool,
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// JPEG 与 AVIF 的质量，1-100
+    pub quality: Option<u8>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizationReport {
+    pub original_size: u64,
+    pub optimized_size: u64,
+    pub saved_bytes: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 响应式图片变体规则，如 `@2x`、缩略图等，按存储桶配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantRule {
+    /// 追加在文件名与扩展名之间，如 `@2x`、`-thumb`
+    pub suffix: String,
+    /// 目标宽度，原图更窄时不放大
+    pub width: u32,
+    #[serde(default)]
+    pub format: OutputFormat,
+    #[serde(default)]
+    pub quality: Option<u8>,
+}
+
+pub struct ImageVariant {
+    pub suffix: String,
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct OptimizedImage {
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub report: OptimizationReport,
+}
+
+pub fn format_of(content_type: &str) -> Option<ImageFormat> {
+    // gif 可能是动图，重新编码会丢失动画，不处理
+    match content_type {
+        "image/jpeg" => Some(ImageFormat::Jpeg),
+        "image/png" => Some(ImageFormat::Png),
+        "image/webp" => Some(ImageFormat::WebP),
+        "image/bmp" => Some(ImageFormat::Bmp),
+        "image/tiff" => Some(ImageFormat::Tiff),
+        _ => None,
+    }
+}
+
+/// 判断指定类型的图片是否可以解码处理
+pub fn is_supported(content_type: &str) -> bool {
+    format_of(content_type).is_some()
+}
+
+impl OutputFormat {
+    fn resolve(self, input: ImageFormat) -> ImageFormat {
+        match self {
+            OutputFormat::Original => match input {
+                // bmp 与 tiff 不适合在网页上使用，转为 png
+                ImageFormat::Bmp | ImageFormat::Tiff => ImageFormat::Png,
+                other => other,
+            },
+            OutputFormat::Jpeg => ImageFormat::Jpeg,
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Avif => ImageFormat::Avif,
+        }
+    }
+}
+
+fn quality_or_default(quality: Option<u8>) -> u8 {
+    quality.unwrap_or(DEFAULT_QUALITY).clamp(1, 100)
+}
+
+impl ImageOptimization {
+    /// 判断指定类型的文件是否需要经过图片优化
+    pub fn applies_to(&self, content_type: &str) -> bool {
+        self.enabled && is_supported(content_type)
+    }
+
+    fn needs_resize(&self, width: u32, height: u32) -> bool {
+        self.max_width.is_some_and(|max| width > max)
+            || self.max_height.is_some_and(|max| height > max)
+    }
+
+    /// 优化已解码的图片，data 为原文件内容，input_format 为其格式。
+    /// 该函数会进行 CPU 密集的编码，应在 spawn_blocking 中调用。
+    /// 不需要处理或处理后反而变大时返回 None；strip_metadata 为 true 时总是使用重新编码的结果，
+    /// 以去除 EXIF/GPS 等元数据
+    pub fn optimize(
+        &self,
+        image: &DynamicImage,
+        data: &[u8],
+        input_format: ImageFormat,
+        strip_metadata: bool,
+    ) -> Result<Option<OptimizedImage>, String> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let output_format = self.format.resolve(input_format);
+        let resize = self.needs_resize(image.width(), image.height());
+        let convert = output_format != input_format;
+
+        let resized;
+        let image = if resize {
+            resized = image.resize(
+                self.max_width.unwrap_or(u32::MAX),
+                self.max_height.unwrap_or(u32::MAX),
+                FilterType::Lanczos3,
+            );
+            &resized
+        } else {
+            image
+        };
+
+        let encoded = encode(image, output_format, quality_or_default(self.quality))?;
+
+        // 仅重新压缩且没有变小时保留原文件；要求去除元数据时总是使用重新编码的结果
+        if !resize && !convert && !strip_metadata && encoded.len() >= data.len() {
+            return Ok(None);
+        }
+
+        let (content_type, extension) = format_info(output_format);
+        Ok(Some(OptimizedImage {
+            report: OptimizationReport {
+                original_size: data.len() as u64,
+                optimized_size: encoded.len() as u64,
+                saved_bytes: (data.len() as u64).saturating_sub(encoded.len() as u64),
+                width: image.width(),
+                height: image.height(),
+            },
+            data: encoded,
+            content_type,
+            extension,
+        }))
+    }
+}
+
+/// 按规则从已解码的原图生成缩放后的图片变体。该函数会进行 CPU 密集的编码，应在 spawn_blocking 中调用
+pub fn generate_variants(
+    rules: &[VariantRule],
+    image: &DynamicImage,
+    input_format: ImageFormat,
+) -> Result<Vec<ImageVariant>, String> {
+    rules
+        .iter()
+        .map(|rule| {
+            if rule.suffix.is_empty() {
+                return Err("图片变体的后缀不能为空".to_string());
+            }
+            if rule.width == 0 {
+                return Err(format!("图片变体 {} 的宽度必须大于 0", rule.suffix));
+            }
+            let scaled;
+            let resized = if image.width() > rule.width {
+                scaled = image.resize(rule.width, u32::MAX, FilterType::Lanczos3);
+                &scaled
+            } else {
+                image
+            };
+            let output_format = rule.format.resolve(input_format);
+            let data = encode(resized, output_format, quality_or_default(rule.quality))?;
+            let (content_type, extension) = format_info(output_format);
+            Ok(ImageVariant {
+                suffix: rule.suffix.clone(),
+                data,
+                content_type,
+                extension,
+                width: resized.width(),
+                height: resized.height(),
+            })
+        })
+        .collect()
+}
+
+/// 解码图片并按 EXIF 方向旋转，因为重新编码后方向信息会丢失。
+/// 像素数超过 MAX_PIXELS 的图片返回错误
+pub fn decode(data: &[u8], format: ImageFormat) -> Result<DynamicImage, String> {
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_ALLOC);
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| format!("无法解码图片：{}", e))?;
+    let (width, height) = decoder.dimensions();
+    if u64::from(width) * u64::from(height) > MAX_PIXELS {
+        return Err(format!("图片尺寸过大：{}x{}", width, height));
+    }
+    let orientation = decoder.orientation().ok();
+    let mut image =
+        DynamicImage::from_decoder(decoder).map_err(|e| format!("无法解码图片：{}", e))?;
+    if let Some(orientation) = orientation {
+        image.apply_orientation(orientation);
+    }
+    Ok(image)
+}
+
+pub fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let result = match format {
+        // JPEG 不支持透明通道
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, quality)),
+        ImageFormat::Png => image.write_with_encoder(PngEncoder::new_with_quality(
+            &mut out,
+            CompressionType::Best,
+            PngFilterType::Adaptive,
+        )),
+        // image 库仅支持无损 WebP 编码，只用于保持原格式的 WebP 图片
+        ImageFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut out)),
+        ImageFormat::Avif => image.write_with_encoder(AvifEncoder::new_with_speed_quality(
+            &mut out, AVIF_SPEED, quality,
+        )),
+        other => return Err(format!("不支持输出格式：{:?}", other)),
+    };
+    result.map_err(|e| format!("无法编码图片：{}", e))?;
+    Ok(out)
+}
+
+pub fn format_info(format: ImageFormat) -> (&'static str, &'static str) {
+    match format {
+        ImageFormat::Jpeg => ("image/jpeg", "jpg"),
+        ImageFormat::WebP => ("image/webp", "webp"),
+        ImageFormat::Avif => ("image/avif", "avif"),
+        _ => ("image/png", "png"),
+    }
+}
+
+/// 替换 key 的扩展名，没有扩展名时追加
+pub fn replace_extension(key: &str, extension: &str) -> String {
+    variant_key(key, "", extension)
+}
+
+/// 在 key 的文件名与扩展名之间插入后缀，并替换扩展名，如 `a/b.png` -> `a/b@2x.webp`
+pub fn variant_key(key: &str, suffix: &str, extension: &str) -> String {
+    let (dir, name) = match key.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, key),
+    };
+    let stem = match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    };
+    match dir {
+        Some(dir) => format!("{}/{}{}.{}", dir, stem, suffix, extension),
+        None => format!("{}{}.{}", stem, suffix, extension),
+    }
+}
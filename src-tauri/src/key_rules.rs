@@ -0,0 +1,174 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+// S3/R2 对象 key 的最大长度（UTF-8 字节）
+pub const MAX_KEY_BYTES: usize = 1024;
+
+// 在 URL 路径段中需要转义的字符：除 RFC 3986 unreserved 字符外全部转义
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// 在 key 中容易导致问题的字符，参考 S3 文档中 "Characters to avoid"
+const RESERVED_CHARS: &[char] = &[
+    '#', '?', '%', '&', '+', '\\', '{', '}', '^', '`', '[', ']', '"', '<', '>', '~', '|', ':', '*',
+];
+
+/// 远程 key 的规范化规则，按存储桶配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KeyRules {
+    /// 将每个路径段转换为 slug：仅保留字母数字，其余字符替换为 `-`
+    pub slugify: bool,
+    pub lowercase: bool,
+    /// 将保留字符替换为 `-`
+    pub replace_reserved: bool,
+    /// key 的最大字节数，不能超过 1024
+    pub max_length: Option<usize>,
+}
+
+fn slugify_segment(segment: &str) -> String {
+    // 保留扩展名前的 `.`
+    let (stem, ext) = match segment.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() => (stem, Some(ext)),
+        _ => (segment, None),
+    };
+
+    let slug = |s: &str| {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            if c.is_alphanumeric() {
+                out.push(c);
+            } else if !out.is_empty() && !out.ends_with('-') {
+                out.push('-');
+            }
+        }
+        out.trim_end_matches('-').to_string()
+    };
+
+    match ext {
+        Some(ext) => format!("{}.{}", slug(stem), slug(ext)),
+        None => slug(stem),
+    }
+}
+
+impl KeyRules {
+    /// 规范化 key：Unicode NFC、统一路径分隔符、去除控制字符与 `.`/`..` 路径段，
+    /// 再按规则进行 slug 化、小写化和保留字符替换，最后检查长度
+    pub fn apply(&self, key: &str) -> Result<String, String> {
+        let key: String = key
+            .nfc()
+            .map(|c| if c == '\\' { '/' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+
+        let mut segments = Vec::new();
+        for segment in key.split('/') {
+            let segment = segment.trim();
+            if segment.is_empty() || segment == "." || segment == ".." {
+                continue;
+            }
+
+            let mut segment = if self.slugify {
+                slugify_segment(segment)
+            } else {
+                segment.to_string()
+            };
+            if self.lowercase {
+                segment = segment.to_lowercase();
+            }
+            if self.replace_reserved {
+                segment = segment.replace(RESERVED_CHARS, "-");
+            }
+            if !segment.is_empty() {
+                segments.push(segment);
+            }
+        }
+
+        let key = segments.join("/");
+        if key.is_empty() {
+            return Err("规范化后的文件名为空".to_string());
+        }
+
+        let max_length = self.max_length.unwrap_or(MAX_KEY_BYTES).min(MAX_KEY_BYTES);
+        if key.len() > max_length {
+            return Err(format!(
+                "文件名过长：{} 字节，最大允许 {} 字节",
+                key.len(),
+                max_length
+            ));
+        }
+
+        Ok(key)
+    }
+}
+
+/// 对 key 的每个路径段分别进行百分号编码
+pub fn encode_key(key: &str) -> String {
+    key.split('/')
+        .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 使用自定义域名和 key 构建公开访问的 URL
+pub fn public_url(domain: &str, key: &str) -> String {
+    format!("{}/{}", domain.trim_end_matches('/'), encode_key(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_normalizes_paths() {
+        let rules = KeyRules::default();
+        assert_eq!(
+            rules.apply("a\\./b/../ c /\u{7}d.txt").unwrap(),
+            "a/b/c/d.txt"
+        );
+        // NFD 的 é 统一为 NFC
+        assert_eq!(rules.apply("cafe\u{301}.txt").unwrap(), "caf\u{e9}.txt");
+        assert!(rules.apply("/./..//").is_err());
+    }
+
+    #[test]
+    fn apply_rules() {
+        let rules = KeyRules {
+            slugify: true,
+            lowercase: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            rules.apply("My Photos/Hello, World!.JPG").unwrap(),
+            "my-photos/hello-world.jpg"
+        );
+
+        let rules = KeyRules {
+            replace_reserved: true,
+            ..Default::default()
+        };
+        assert_eq!(rules.apply("a#b?c[1].txt").unwrap(), "a-b-c-1-.txt");
+    }
+
+    #[test]
+    fn apply_max_length() {
+        let rules = KeyRules {
+            max_length: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(rules.apply("a.txt").unwrap(), "a.txt");
+        assert!(rules.apply("ab.txt").is_err());
+
+        // 超过 1024 的配置按 1024 处理
+        let rules = KeyRules {
+            max_length: Some(4096),
+            ..Default::default()
+        };
+        assert!(rules.apply(&"a".repeat(MAX_KEY_BYTES)).is_ok());
+        assert!(rules.apply(&"a".repeat(MAX_KEY_BYTES + 1)).is_err());
+    }
+}
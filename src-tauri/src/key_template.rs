@@ -0,0 +1,247 @@
+use crate::source::InlineContent;
+use crate::template;
+use crate::typ::{File, UploadSource};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+
+/// 渲染 key 模板所需的上下文
+pub struct KeyContext<'a> {
+    /// 前端传入的原始 key，{filename}、{ext}、{relative_dir} 由它推出
+    pub original: &'a str,
+    pub now: DateTime<Local>,
+    /// 文件内容的 sha256（十六进制），仅当模板中使用了 {sha256} 时需要
+    pub sha256: Option<String>,
+}
+
+// {random:N} 的最大长度
+const MAX_RANDOM_LEN: usize = 64;
+
+pub fn needs_hash(template: &str) -> bool {
+    template.contains("{sha256")
+}
+
+fn split_original(original: &str) -> (&str, &str, &str) {
+    let (dir, name) = original.rsplit_once('/').unwrap_or(("", original));
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (dir, stem, ext),
+        _ => (dir, name, ""),
+    }
+}
+
+fn parse_len(arg: Option<&str>, default: usize) -> Result<usize, String> {
+    match arg {
+        None => Ok(default),
+        Some(arg) => arg
+            .parse()
+            .map_err(|_| format!("模板变量长度参数无效：{}", arg)),
+    }
+}
+
+fn render_variable(var: &str, ctx: &KeyContext) -> Result<String, String> {
+    let (name, arg) = match var.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (var, None),
+    };
+    let (dir, stem, ext) = split_original(ctx.original);
+
+    Ok(match name {
+        "yyyy" => format!("{:04}", ctx.now.year()),
+        "yy" => format!("{:02}", ctx.now.year() % 100),
+        "mm" => format!("{:02}", ctx.now.month()),
+        "dd" => format!("{:02}", ctx.now.day()),
+        "hh" => format!("{:02}", ctx.now.hour()),
+        "mi" => format!("{:02}", ctx.now.minute()),
+        "ss" => format!("{:02}", ctx.now.second()),
+        "timestamp" => ctx.now.timestamp().to_string(),
+        "filename" => stem.to_string(),
+        "ext" => ext.to_string(),
+        "relative_dir" => dir.to_string(),
+        "uuid" => Uuid::new_v4().to_string(),
+        "random" => {
+            let len = parse_len(arg, 8)?;
+            if len > MAX_RANDOM_LEN {
+                return Err(format!("{{random}} 的长度不能超过 {}", MAX_RANDOM_LEN));
+            }
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(len)
+                .map(char::from)
+                .collect::<String>()
+                .to_lowercase()
+        }
+        "sha256" => {
+            let hash = ctx
+                .sha256
+                .as_deref()
+                .ok_or_else(|| "缺少文件哈希".to_string())?;
+            let len = parse_len(arg, hash.len())?.min(hash.len());
+            hash[..len].to_string()
+        }
+        _ => return Err(format!("未知的模板变量：{{{}}}", var)),
+    })
+}
+
+/// 渲染 key 模板，`{{` 与 `}}` 分别输出字面量 `{` 与 `}`。
+/// 变量为空时（如 {relative_dir}）会产生多余的 `/`，渲染后统一合并；
+/// 没有扩展名时去掉紧挨在 {ext} 前的 `.`，`{filename}.{ext}` 不会留下结尾的点
+pub fn render(template: &str, ctx: &KeyContext) -> Result<String, String> {
+    let out = template::render(template, |var, out| {
+        let value = render_variable(var, ctx)?;
+        if var == "ext" && value.is_empty() && out.ends_with('.') {
+            out.pop();
+        }
+        out.push_str(&value);
+        Ok(())
+    })?;
+
+    let key = out
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    if key.is_empty() {
+        return Err("模板渲染结果为空".to_string());
+    }
+    Ok(key)
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub async fn sha256_file(path: &str) -> Result<String, String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 计算文件最终的远程 key。文件自身的模板优先，其次是存储桶的默认模板，
+/// 都没有时直接使用前端传入的 remote_filename。
+/// content 为已读入内存的上传内容，计算 {sha256} 时优先使用，为空时按来源读取
+pub async fn resolve_key(
+    file: &File,
+    default_template: Option<&str>,
+    content: Option<&[u8]>,
+) -> Result<String, String> {
+    let template = file
+        .key_template
+        .as_deref()
+        .or(default_template)
+        .filter(|t| !t.is_empty());
+    let Some(template) = template else {
+        return Ok(file.remote_filename.clone());
+    };
+
+    let sha256 = if needs_hash(template) {
+        Some(match (content, &file.source) {
+            (Some(data), _) => sha256_hex(data),
+            (None, UploadSource::FilePath(path)) => sha256_file(path).await?,
+            // 已知长度的远程内容边下载边上传，无法预先计算哈希
+            (None, UploadSource::Url(_)) => {
+                return Err("边下载边上传的远程 URL 不支持 {sha256} 模板变量".to_string())
+            }
+            (None, source) => sha256_hex(&InlineContent::decode(source)?.data),
+        })
+    } else {
+        None
+    };
+
+    render(
+        template,
+        &KeyContext {
+            original: &file.remote_filename,
+            now: Local::now(),
+            sha256,
+        },
+    )
+}
+
+/// 预览模板渲染结果，sha256 使用占位值
+#[tauri::command]
+pub fn key_template_preview(template: String, filename: String) -> Result<String, String> {
+    render(
+        &template,
+        &KeyContext {
+            original: &filename,
+            now: Local::now(),
+            sha256: Some(sha256_hex(filename.as_bytes())),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ctx(original: &str) -> KeyContext<'_> {
+        KeyContext {
+            original,
+            now: Local.with_ymd_and_hms(2024, 3, 5, 7, 8, 9).unwrap(),
+            sha256: Some(sha256_hex(b"hello")),
+        }
+    }
+
+    #[test]
+    fn render_variables() {
+        let ctx = ctx("photos/cat.final.png");
+        assert_eq!(
+            render("{yyyy}/{mm}/{dd}/{hh}{mi}{ss}-{filename}.{ext}", &ctx).unwrap(),
+            "2024/03/05/070809-cat.final.png"
+        );
+        assert_eq!(
+            render("{relative_dir}/{sha256:8}.{ext}", &ctx).unwrap(),
+            "photos/2cf24dba.png"
+        );
+        assert_eq!(render("{yy}-{ filename }", &ctx).unwrap(), "24-cat.final");
+        assert_eq!(render("{random:12}", &ctx).unwrap().len(), 12);
+    }
+
+    #[test]
+    fn render_escapes_and_empty_segments() {
+        let ctx = ctx(".env");
+        assert_eq!(render("{{{filename}}}", &ctx).unwrap(), "{.env}");
+        // {relative_dir} 为空时不会留下多余的 `/`
+        assert_eq!(render("/{relative_dir}//{filename}", &ctx).unwrap(), ".env");
+        assert_eq!(render("{ext}", &ctx).unwrap_err(), "模板渲染结果为空");
+    }
+
+    #[test]
+    fn render_without_extension() {
+        let ctx = ctx("docs/README");
+        assert_eq!(render("{filename}.{ext}", &ctx).unwrap(), "README");
+        assert_eq!(
+            render("{relative_dir}/{filename}-{random:0}.{ ext }", &ctx).unwrap(),
+            "docs/README-"
+        );
+        assert_eq!(render("{filename}.{{ext}}", &ctx).unwrap(), "README.{ext}");
+    }
+
+    #[test]
+    fn render_errors() {
+        let ctx = ctx("a.txt");
+        assert!(render("{filename", &ctx).is_err());
+        assert!(render("{unknown}", &ctx).is_err());
+        assert!(render("{random:x}", &ctx).is_err());
+        assert!(render("{random:65}", &ctx).is_err());
+        let ctx = KeyContext {
+            sha256: None,
+            ..ctx
+        };
+        assert!(render("{sha256}", &ctx).is_err());
+    }
+}
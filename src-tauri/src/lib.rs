@@ -1,8 +1,34 @@
 use tauri::Manager;
 
+mod batch;
+mod clipboard_watch;
+mod compression;
+mod crypto;
+mod encryption;
+mod history;
+mod hooks;
+mod ignore_rules;
+mod image_pipeline;
+mod key_rules;
+mod key_template;
+mod link_format;
 mod manager;
+mod mime_detect;
+mod pipeline;
+mod preview;
+mod privacy;
+mod profile;
+mod profile_import;
 mod r2;
+mod remote;
+mod scan;
+mod source;
+mod sse;
+mod template;
+mod text_preview;
+mod thumbnail;
 mod typ;
+mod upload_options;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -25,12 +51,46 @@ pub fn run() {
     let builder = builder.plugin(tauri_plugin_clipboard::init());
 
     builder
+        .setup(|app| {
+            clipboard_watch::restore(app.handle());
+            Ok(())
+        })
+        .register_asynchronous_uri_scheme_protocol(thumbnail::SCHEME, |ctx, request, responder| {
+            thumbnail::handle_request(ctx.app_handle().clone(), request, responder)
+        })
         .invoke_handler(tauri::generate_handler![
+            batch::batch_report,
+            batch::batch_export,
+            clipboard_watch::clipboard_watch_start,
+            clipboard_watch::clipboard_watch_stop,
+            clipboard_watch::clipboard_watch_status,
+            history::history_list,
+            history::history_get,
+            history::history_delete,
+            history::history_export,
+            key_template::key_template_preview,
             manager::preview_file,
             manager::get_file_details,
+            profile::profile_status,
+            profile::profile_unlock,
+            profile::profile_lock,
+            profile::profile_list,
+            profile::profile_add,
+            profile::profile_update,
+            profile::profile_remove,
+            profile_import::profile_import,
+            profile_import::profile_import_commit,
+            profile_import::profile_export_rclone,
             r2::r2_ping,
             r2::r2_upload,
             r2::r2_cancel_upload,
+            r2::r2_download,
+            r2::r2_head_object,
+            r2::r2_copy_object,
+            scan::scan_start,
+            scan::scan_cancel,
+            sse::sse_generate_key,
+            thumbnail::thumbnail_clear_cache,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,267 @@
+use crate::template;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::{Deserialize, Serialize};
+
+// 在 Markdown、BBCode、RST 链接中有特殊含义的字符，URL 中出现时进行百分号编码
+const URL_UNSAFE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'(')
+    .add(b')')
+    .add(b'[')
+    .add(b']')
+    .add(b'`');
+
+/// 上传成功后生成的链接格式。未加密的图片生成内嵌图片，其他文件生成普通链接
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkFormat {
+    #[default]
+    Url,
+    Markdown,
+    Html,
+    BbCode,
+    // reStructuredText
+    Rst,
+    // 引用 LinkSettings.templates 中同名的自定义模板
+    Custom(String),
+}
+
+/// 用户自定义的链接模板，支持 {url}、{filename}、{key}、{width}、{height}、{size}
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkTemplate {
+    pub name: String,
+    pub template: String,
+}
+
+/// 按存储桶配置的链接格式
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LinkSettings {
+    // 复制链接时默认使用的格式
+    pub default_format: LinkFormat,
+    pub templates: Vec<LinkTemplate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedLink {
+    pub format: LinkFormat,
+    pub text: String,
+}
+
+/// 生成链接所需的对象信息
+pub struct LinkContext<'a> {
+    pub url: &'a str,
+    pub key: &'a str,
+    pub content_type: &'a str,
+    pub size: u64,
+    // 图片的宽高，其他文件为空
+    pub dimensions: Option<(u32, u32)>,
+    // 使用客户端加密上传，对象内容是密文
+    pub encrypted: bool,
+}
+
+impl LinkContext<'_> {
+    fn filename(&self) -> &str {
+        self.key.rsplit('/').next().unwrap_or(self.key)
+    }
+
+    // 密文无法被浏览器显示，加密的图片也生成普通链接
+    fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/") && !self.encrypted
+    }
+}
+
+const BUILTIN_FORMATS: [LinkFormat; 5] = [
+    LinkFormat::Url,
+    LinkFormat::Markdown,
+    LinkFormat::Html,
+    LinkFormat::BbCode,
+    LinkFormat::Rst,
+];
+
+impl LinkSettings {
+    /// 生成所有内置格式与自定义模板的链接，默认格式排在第一个。
+    /// 无法渲染的自定义模板会被跳过
+    pub fn format_all(&self, ctx: &LinkContext) -> Vec<FormattedLink> {
+        let custom = self
+            .templates
+            .iter()
+            .map(|template| LinkFormat::Custom(template.name.clone()));
+        let mut links = BUILTIN_FORMATS
+            .into_iter()
+            .chain(custom)
+            .filter_map(|format| {
+                let text = self.format(&format, ctx).ok()?;
+                Some(FormattedLink { format, text })
+            })
+            .collect::<Vec<_>>();
+        if let Some(index) = links
+            .iter()
+            .position(|link| link.format == self.default_format)
+        {
+            let link = links.remove(index);
+            links.insert(0, link);
+        }
+        links
+    }
+
+    pub fn format(&self, format: &LinkFormat, ctx: &LinkContext) -> Result<String, String> {
+        let url = ctx.url;
+        let name = ctx.filename();
+        let safe_url = utf8_percent_encode(url, URL_UNSAFE).to_string();
+        Ok(match format {
+            LinkFormat::Url => url.to_string(),
+            LinkFormat::Markdown if ctx.is_image() => {
+                format!("![{}]({})", escape_markdown(name), safe_url)
+            }
+            LinkFormat::Markdown => format!("[{}]({})", escape_markdown(name), safe_url),
+            LinkFormat::Html if ctx.is_image() => {
+                let size = ctx
+                    .dimensions
+                    .map(|(width, height)| format!(" width=\"{}\" height=\"{}\"", width, height))
+                    .unwrap_or_default();
+                format!(
+                    "<img src=\"{}\" alt=\"{}\"{}>",
+                    escape_html(url),
+                    escape_html(name),
+                    size
+                )
+            }
+            LinkFormat::Html => {
+                format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(name))
+            }
+            LinkFormat::BbCode if ctx.is_image() => format!("[img]{}[/img]", safe_url),
+            LinkFormat::BbCode => format!("[url={}]{}[/url]", safe_url, escape_bbcode(name)),
+            LinkFormat::Rst if ctx.is_image() => {
+                format!(".. image:: {}\n   :alt: {}", safe_url, escape_rst(name))
+            }
+            LinkFormat::Rst => format!("`{} <{}>`_", escape_rst(name), safe_url),
+            LinkFormat::Custom(name) => {
+                let template = self
+                    .templates
+                    .iter()
+                    .find(|template| &template.name == name)
+                    .ok_or_else(|| format!("链接模板不存在：{}", name))?;
+                render(&template.template, ctx)?
+            }
+        })
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '[' | ']' | '\\' | '*' | '_' | '`' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+// BBCode 没有转义语法，方括号使用 HTML 实体
+fn escape_bbcode(text: &str) -> String {
+    text.replace('[', "&#91;").replace(']', "&#93;")
+}
+
+fn escape_rst(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '[' | ']' | '\\' | '*' | '_' | '`' | '<' | '>' | '|' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// 渲染自定义链接模板，`{{` 与 `}}` 分别输出字面量 `{` 与 `}`。非图片的 {width}、{height} 为空
+pub fn render(template: &str, ctx: &LinkContext) -> Result<String, String> {
+    template::render(template, |var, out| {
+        out.push_str(&match var {
+            "url" => ctx.url.to_string(),
+            "filename" => ctx.filename().to_string(),
+            "key" => ctx.key.to_string(),
+            "size" => ctx.size.to_string(),
+            "width" => ctx
+                .dimensions
+                .map(|(w, _)| w.to_string())
+                .unwrap_or_default(),
+            "height" => ctx
+                .dimensions
+                .map(|(_, h)| h.to_string())
+                .unwrap_or_default(),
+            _ => return Err(format!("未知的模板变量：{{{}}}", var)),
+        });
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx<'a>(
+        url: &'a str,
+        key: &'a str,
+        content_type: &'a str,
+        encrypted: bool,
+    ) -> LinkContext<'a> {
+        LinkContext {
+            url,
+            key,
+            content_type,
+            size: 10,
+            dimensions: Some((2, 1)),
+            encrypted,
+        }
+    }
+
+    fn format(format: LinkFormat, ctx: &LinkContext) -> String {
+        LinkSettings::default().format(&format, ctx).unwrap()
+    }
+
+    #[test]
+    fn escapes_urls_and_names() {
+        let ctx = ctx("https://a.com/x (1).txt", "x [1]_.txt", "text/plain", false);
+        assert_eq!(
+            format(LinkFormat::Markdown, &ctx),
+            "[x \\[1\\]\\_.txt](https://a.com/x%20%281%29.txt)"
+        );
+        assert_eq!(
+            format(LinkFormat::BbCode, &ctx),
+            "[url=https://a.com/x%20%281%29.txt]x &#91;1&#93;_.txt[/url]"
+        );
+        assert_eq!(
+            format(LinkFormat::Rst, &ctx),
+            "`x \\[1\\]\\_.txt <https://a.com/x%20%281%29.txt>`_"
+        );
+        assert_eq!(format(LinkFormat::Url, &ctx), "https://a.com/x (1).txt");
+    }
+
+    #[test]
+    fn encrypted_images_use_links() {
+        let image = ctx("https://a.com/a.png", "a.png", "image/png", false);
+        assert_eq!(
+            format(LinkFormat::Html, &image),
+            "<img src=\"https://a.com/a.png\" alt=\"a.png\" width=\"2\" height=\"1\">"
+        );
+        let encrypted = ctx("https://a.com/a.png", "a.png", "image/png", true);
+        assert_eq!(
+            format(LinkFormat::Markdown, &encrypted),
+            "[a.png](https://a.com/a.png)"
+        );
+        assert_eq!(
+            format(LinkFormat::Html, &encrypted),
+            "<a href=\"https://a.com/a.png\">a.png</a>"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use mime_guess::from_path;
+use std::collections::HashMap;
+use std::io::Read;
+use tokio::io::AsyncReadExt;
+
+// 读取文件头用于嗅探的字节数，MPEG-TS 需要至少两个 188 字节的包
+pub const SNIFF_LEN: usize = 8192;
+
+const TS_PACKET_LEN: usize = 188;
+
+// 魔数检测结果过于笼统时，优先使用扩展名推断的类型（如 .docx、.apk 实际都是 zip）
+const GENERIC_TYPES: &[&str] = &["application/zip", "application/x-ole-storage", "text/plain"];
+
+fn extension(path: &str) -> Option<String> {
+    std::path::Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+fn is_mpeg_ts(header: &[u8]) -> bool {
+    header.len() >= TS_PACKET_LEN * 2
+        && header
+            .iter()
+            .step_by(TS_PACKET_LEN)
+            .take(header.len() / TS_PACKET_LEN)
+            .all(|b| *b == 0x47)
+}
+
+/// 根据文件头的魔数嗅探 MIME 类型
+pub fn sniff(header: &[u8]) -> Option<&'static str> {
+    if let Some(kind) = infer::get(header) {
+        return Some(kind.mime_type());
+    }
+    if is_mpeg_ts(header) {
+        return Some("video/mp2t");
+    }
+    None
+}
+
+fn guess(path: &str) -> Option<String> {
+    from_path(path).first().map(|mime| mime.to_string())
+}
+
+/// 检测对象的 Content-Type，优先级：用户按扩展名配置的覆盖 > 文件头魔数 > 内容声明的类型 > 扩展名推断。
+/// key 与本地路径的扩展名都会参与判断，因为 key 可能已被模板改写。
+/// declared 为内容自带的类型，如 data URL 中的 MIME
+pub fn detect(
+    header: &[u8],
+    key: &str,
+    local_path: Option<&str>,
+    declared: Option<&str>,
+    overrides: &HashMap<String, String>,
+) -> String {
+    let extensions = [Some(key), local_path]
+        .into_iter()
+        .flatten()
+        .filter_map(extension)
+        .collect::<Vec<_>>();
+
+    for ext in &extensions {
+        if let Some(mime) = overrides.get(ext.as_str()) {
+            return mime.clone();
+        }
+    }
+
+    let by_extension = declared.map(str::to_string).or_else(|| {
+        [Some(key), local_path]
+            .into_iter()
+            .flatten()
+            .find_map(guess)
+    });
+
+    match (sniff(header), by_extension) {
+        (Some(sniffed), Some(by_extension)) if GENERIC_TYPES.contains(&sniffed) => by_extension,
+        (Some(sniffed), _) => sniffed.to_string(),
+        (None, Some(by_extension)) => by_extension,
+        (None, None) => "application/octet-stream".to_string(),
+    }
+}
+
+pub async fn read_header(path: &str) -> Result<Vec<u8>, String> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    file.take(SNIFF_LEN as u64)
+        .read_to_end(&mut header)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(header)
+}
+
+// 与 read_header 相同，用于目录扫描等阻塞线程
+pub fn read_header_blocking(path: &str) -> Result<Vec<u8>, String> {
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    file.take(SNIFF_LEN as u64)
+        .read_to_end(&mut header)
+        .map_err(|e| e.to_string())?;
+    Ok(header)
+}
+
+/// 规范化用户配置的扩展名覆盖表：扩展名小写并去掉前导 `.`
+pub fn normalize_overrides(overrides: &HashMap<String, String>) -> HashMap<String, String> {
+    overrides
+        .iter()
+        .map(|(ext, mime)| {
+            (
+                ext.trim().trim_start_matches('.').to_lowercase(),
+                mime.trim().to_string(),
+            )
+        })
+        .collect()
+}
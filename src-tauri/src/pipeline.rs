@@ -0,0 +1,407 @@
+use crate::encryption::ObjectCipher;
+use crate::image_pipeline::{self, ImageVariant, OptimizedImage};
+use crate::key_template;
+use crate::mime_detect;
+use crate::privacy;
+use crate::profile::BucketProfile;
+use crate::remote;
+use crate::source::{self, InlineContent};
+use crate::typ::{File, UploadDetails, UploadSource};
+use crate::upload_options::{self, UploadOptions};
+use image::ImageReader;
+use once_cell::sync::Lazy;
+use percent_encoding::{utf8_percent_encode, CONTROLS};
+use std::io::Cursor;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// 同时在内存中处理的文件数。去除元数据、图片处理与压缩都需要将整个文件读入内存，
+// 批量上传时所有文件同时开始，不加限制会占用大量内存
+const MAX_IN_MEMORY: usize = 2;
+static IN_MEMORY: Lazy<Arc<Semaphore>> = Lazy::new(|| Arc::new(Semaphore::new(MAX_IN_MEMORY)));
+
+/// 实际上传的内容
+pub enum UploadBody {
+    /// 直接从本地文件流式上传
+    File(String),
+    /// 上传内存中的数据，如文本内容或经过处理的图片
+    Bytes(Vec<u8>),
+    /// 长度已知的数据流，如远程 URL 的响应体
+    Stream {
+        reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+        size: u64,
+    },
+}
+
+/// 随原文件一起上传的图片变体
+pub struct PreparedVariant {
+    pub key: String,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    pub options: UploadOptions,
+}
+
+/// 经过上传前处理后的文件
+pub struct PreparedUpload {
+    pub key: String,
+    // 处理后实际上传的内容大小
+    pub size: u64,
+    // 加密前的 Content-Type
+    pub content_type: String,
+    // 图片的宽高，其他文件为空
+    pub dimensions: Option<(u32, u32)>,
+    pub options: UploadOptions,
+    pub body: UploadBody,
+    pub variants: Vec<PreparedVariant>,
+    pub details: UploadDetails,
+    // 内容需要在内存中处理时持有，上传结束后随 PreparedUpload 一起释放
+    pub memory_permit: Option<OwnedSemaphorePermit>,
+}
+
+// 将需要处理的文件读入内存
+async fn read_body(body: UploadBody, max_size: u64) -> Result<Vec<u8>, String> {
+    match body {
+        UploadBody::File(path) => {
+            let size = tokio::fs::metadata(&path)
+                .await
+                .map_err(|e| e.to_string())?
+                .len();
+            if size > max_size {
+                return Err(format!("文件过大，无法处理：{}", path));
+            }
+            tokio::fs::read(&path)
+                .await
+                .map_err(|e| format!("无法读取文件：{}", e))
+        }
+        UploadBody::Bytes(data) => Ok(data),
+        UploadBody::Stream { mut reader, size } => {
+            if size > max_size {
+                return Err("远程文件过大，无法处理".to_string());
+            }
+            let mut data = Vec::new();
+            reader
+                .read_to_end(&mut data)
+                .await
+                .map_err(|e| format!("下载远程文件失败：{}", e))?;
+            Ok(data)
+        }
+    }
+}
+
+// 获取内存处理的许可，已持有时直接返回
+async fn acquire_memory(permit: &mut Option<OwnedSemaphorePermit>) -> Result<(), String> {
+    if permit.is_none() {
+        *permit = Some(
+            IN_MEMORY
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| e.to_string())?,
+        );
+    }
+    Ok(())
+}
+
+// 内容大小，不读取内容
+async fn body_size(body: &UploadBody) -> Result<u64, String> {
+    match body {
+        UploadBody::File(path) => tokio::fs::metadata(path)
+            .await
+            .map(|metadata| metadata.len())
+            .map_err(|e| e.to_string()),
+        UploadBody::Bytes(data) => Ok(data.len() as u64),
+        UploadBody::Stream { size, .. } => Ok(*size),
+    }
+}
+
+// 只读取图片头部获取宽高
+async fn image_dimensions(body: &UploadBody) -> Option<(u32, u32)> {
+    match body {
+        UploadBody::File(path) => {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || {
+                ImageReader::open(path)
+                    .ok()?
+                    .with_guessed_format()
+                    .ok()?
+                    .into_dimensions()
+                    .ok()
+            })
+            .await
+            .ok()
+            .flatten()
+        }
+        UploadBody::Bytes(data) => ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok(),
+        // 流只能读取一次，不读取宽高
+        UploadBody::Stream { .. } => None,
+    }
+}
+
+// 去除失败时仍返回原始数据，由调用方决定是否继续上传
+async fn strip_metadata(
+    data: Vec<u8>,
+    content_type: String,
+) -> Result<(Vec<u8>, Result<Option<Vec<u8>>, String>), String> {
+    tokio::task::spawn_blocking(move || {
+        let stripped = privacy::strip(&data, &content_type);
+        (data, stripped)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// 优化图片并生成变体，变体从原图生成以保证质量。
+// strip_metadata 为 true 时即使不需要缩放或转换格式也重新编码
+async fn process_image(
+    profile: &BucketProfile,
+    data: Vec<u8>,
+    content_type: String,
+    strip_metadata: bool,
+) -> Result<(Vec<u8>, Option<OptimizedImage>, Vec<ImageVariant>), String> {
+    let optimization = profile.image_optimization.clone();
+    let rules = profile.image_variants.clone();
+    tokio::task::spawn_blocking(move || {
+        let Some(format) = image_pipeline::format_of(&content_type) else {
+            return Ok((data, None, Vec::new()));
+        };
+        // 原图只解码一次，优化与变体共用
+        let image = image_pipeline::decode(&data, format)?;
+        let variants = image_pipeline::generate_variants(&rules, &image, format)?;
+        let optimized = optimization.optimize(&image, &data, format, strip_metadata)?;
+        Ok((data, optimized, variants))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// 生成上传选项：按规则压缩，启用客户端加密时为每个对象创建独立的加密器
+async fn build_options(
+    file: &File,
+    profile: &BucketProfile,
+    content_type: String,
+    size: u64,
+) -> Result<UploadOptions, String> {
+    let compression = profile.compression.plan(&content_type, size);
+    let mut options = UploadOptions::new(
+        content_type,
+        &upload_options::original_filename(file),
+        &file.headers.merged(&profile.default_headers),
+    )?;
+    options.compression = compression;
+
+    if let Some(cipher) = ObjectCipher::new(&profile.encryption).await? {
+        // 密文无法被浏览器或 CDN 解压，加密时不再压缩；原始 Content-Type 保存在元数据中
+        options.compression = None;
+        options
+            .metadata
+            .extend(cipher.metadata(&options.content_type, size));
+        options.content_type = "application/octet-stream".to_string();
+        options.cipher = Some(cipher);
+    }
+    Ok(options)
+}
+
+/// 上传前处理：计算远程 key、检测类型、去除元数据、优化图片、生成图片变体，并生成压缩、加密等上传选项
+pub async fn prepare_upload(
+    file: &File,
+    profile: &BucketProfile,
+) -> Result<PreparedUpload, String> {
+    let max_remote_size = profile.max_remote_size.unwrap_or(remote::DEFAULT_MAX_SIZE);
+    let remote = match &file.source {
+        UploadSource::Url(url) => Some(remote::open(url, max_remote_size).await?),
+        _ => None,
+    };
+    // 远程 URL 未指定文件名时使用响应头或 URL 中的文件名
+    let renamed;
+    let file = match (&file.source, &remote) {
+        (UploadSource::Url(url), Some(source)) if file.remote_filename.trim().is_empty() => {
+            renamed = File {
+                id: file.id.clone(),
+                source: UploadSource::Url(url.clone()),
+                remote_filename: source.filename.clone(),
+                key_template: file.key_template.clone(),
+                headers: file.headers.clone(),
+            };
+            &renamed
+        }
+        _ => file,
+    };
+
+    let mut details = UploadDetails::default();
+    let mut memory_permit = None;
+    let mut remote_header = Vec::new();
+    let (mut body, local_path, declared_type) = match (&file.source, remote) {
+        (UploadSource::FilePath(path), _) => {
+            (UploadBody::File(path.clone()), Some(path.as_str()), None)
+        }
+        // 服务器未提供长度时先读入内存
+        (_, Some(source)) => match source.size {
+            Some(size) => {
+                remote_header = source.header;
+                let body = UploadBody::Stream {
+                    reader: source.reader,
+                    size,
+                };
+                (body, None, source.content_type)
+            }
+            None => {
+                let content_type = source.content_type.clone();
+                let data = source.read_to_end(max_remote_size).await?;
+                (UploadBody::Bytes(data), None, content_type)
+            }
+        },
+        (source, None) => {
+            let content = InlineContent::decode(source)?;
+            (UploadBody::Bytes(content.data), None, content.content_type)
+        }
+    };
+
+    // 根据 key 模板计算最终的远程文件名，并进行规范化
+    let content = match &body {
+        UploadBody::Bytes(data) => Some(data.as_slice()),
+        _ => None,
+    };
+    let key = key_template::resolve_key(file, profile.key_template.as_deref(), content).await?;
+    let mut key = profile.key_rules.apply(&key)?;
+
+    let header = match &body {
+        UploadBody::File(path) => mime_detect::read_header(path).await?,
+        UploadBody::Bytes(data) => data[..data.len().min(mime_detect::SNIFF_LEN)].to_vec(),
+        UploadBody::Stream { .. } => remote_header,
+    };
+    let mut content_type = mime_detect::detect(
+        &header,
+        &key,
+        local_path,
+        declared_type.as_deref(),
+        &mime_detect::normalize_overrides(&profile.mime_overrides),
+    );
+
+    // 去除元数据失败或文件过大时不中断上传，上传原文件并记录警告
+    if profile.strip_metadata && privacy::applies_to(&content_type) {
+        if body_size(&body).await? > privacy::MAX_SIZE {
+            details.warnings.push(format!(
+                "文件超过 {}MB，未去除元数据",
+                privacy::MAX_SIZE / 1024 / 1024
+            ));
+        } else {
+            acquire_memory(&mut memory_permit).await?;
+            let data = read_body(body, privacy::MAX_SIZE).await?;
+            let (data, stripped) = strip_metadata(data, content_type.clone()).await?;
+            body = match stripped {
+                Ok(Some(stripped)) => {
+                    details.metadata_stripped = true;
+                    UploadBody::Bytes(stripped)
+                }
+                Ok(None) => UploadBody::Bytes(data),
+                Err(e) => {
+                    details.warnings.push(format!("无法去除元数据：{}", e));
+                    UploadBody::Bytes(data)
+                }
+            };
+        }
+    }
+
+    let mut variants = Vec::new();
+    let has_variants =
+        !profile.image_variants.is_empty() && image_pipeline::is_supported(&content_type);
+    let mut process = has_variants || profile.image_optimization.applies_to(&content_type);
+    // 过大的图片解码需要大量内存，跳过优化与变体，直接上传原文件
+    if process {
+        let too_large = body_size(&body).await? > image_pipeline::MAX_SIZE
+            || image_dimensions(&body)
+                .await
+                .is_some_and(|(width, height)| {
+                    u64::from(width) * u64::from(height) > image_pipeline::MAX_PIXELS
+                });
+        if too_large {
+            details
+                .warnings
+                .push("图片过大，未进行优化或生成变体".to_string());
+            process = false;
+        }
+    }
+    if process {
+        acquire_memory(&mut memory_permit).await?;
+        let data = read_body(body, image_pipeline::MAX_SIZE).await?;
+        let (data, optimized, image_variants) = process_image(
+            profile,
+            data,
+            content_type.clone(),
+            // 元数据已经去除时不需要再为此重新编码
+            profile.strip_metadata && !details.metadata_stripped,
+        )
+        .await?;
+        for variant in image_variants {
+            let variant_key = profile.key_rules.apply(&image_pipeline::variant_key(
+                &key,
+                &variant.suffix,
+                variant.extension,
+            ))?;
+            variants.push(PreparedVariant {
+                options: build_options(
+                    file,
+                    profile,
+                    variant.content_type.to_string(),
+                    variant.data.len() as u64,
+                )
+                .await?,
+                key: variant_key,
+                width: variant.width,
+                height: variant.height,
+                data: variant.data,
+            });
+        }
+        body = match optimized {
+            Some(optimized) => {
+                // 格式转换后同步修改 key 的扩展名
+                if optimized.content_type != content_type {
+                    key = image_pipeline::replace_extension(&key, optimized.extension);
+                    content_type = optimized.content_type.to_string();
+                }
+                details.optimization = Some(optimized.report);
+                UploadBody::Bytes(optimized.data)
+            }
+            None => UploadBody::Bytes(data),
+        };
+    }
+
+    let size = body_size(&body).await?;
+    let dimensions = match &details.optimization {
+        Some(report) => Some((report.width, report.height)),
+        None if content_type.starts_with("image/") => image_dimensions(&body).await,
+        None => None,
+    };
+    let mut options = build_options(file, profile, content_type.clone(), size).await?;
+    // 压缩时整个文件在上传时读入内存
+    if options.compression.is_some() {
+        acquire_memory(&mut memory_permit).await?;
+    }
+    // 加密时链接目标只保存在密文中
+    if let (UploadSource::Symlink(_), UploadBody::Bytes(target), None) =
+        (&file.source, &body, &options.cipher)
+    {
+        options.metadata.insert(
+            source::SYMLINK_TARGET_META.to_string(),
+            utf8_percent_encode(&String::from_utf8_lossy(target), CONTROLS).to_string(),
+        );
+    }
+
+    Ok(PreparedUpload {
+        key,
+        size,
+        content_type,
+        dimensions,
+        options,
+        body,
+        variants,
+        details,
+        memory_permit,
+    })
+}
@@ -0,0 +1,442 @@
+use crate::mime_detect;
+use crate::text_preview::{self, TextPreview};
+use crate::thumbnail;
+use base64::{engine::general_purpose, Engine};
+use flate2::read::GzDecoder;
+use image::{imageops::FilterType, ImageFormat, ImageReader};
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use symphonia::core::formats::{FormatOptions, Track};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision};
+use symphonia::core::probe::Hint;
+use zip::ZipArchive;
+
+// SVG 需要整体读入并返回给前端，文本最多读取开头的这些字节
+const MAX_INLINE_SIZE: u64 = 10 * 1024 * 1024;
+const MAX_PDF_SIZE: u64 = 100 * 1024 * 1024;
+const MAX_ARCHIVE_ENTRIES: usize = 200;
+const HEX_DUMP_LEN: usize = 512;
+const COVER_SIZE: u32 = 256;
+
+const THUMBNAIL_IMAGES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/webp",
+    "image/gif",
+    "image/bmp",
+    "image/tiff",
+];
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreviewOptions {
+    /// 图片缩略图的最大边长
+    pub max_dimension: Option<u32>,
+    pub max_lines: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            max_dimension: None,
+            max_lines: 100,
+            max_bytes: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePreview {
+    pub mime_type: String,
+    pub size: u64,
+    pub content: PreviewContent,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum PreviewContent {
+    /// 图片通过缩略图协议加载，宽高为原图尺寸
+    Image {
+        url: String,
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+    Svg {
+        data_url: String,
+    },
+    Text(TextPreview),
+    Pdf(PdfInfo),
+    /// 音视频的时长、轨道、标签与封面。视频轨道只有时长，编码未知
+    Media(MediaInfo),
+    Archive(ArchiveListing),
+    Binary {
+        hex_dump: String,
+    },
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfInfo {
+    pub page_count: usize,
+    pub version: String,
+    pub encrypted: bool,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub duration_secs: Option<f64>,
+    pub tracks: Vec<MediaTrack>,
+    pub tags: HashMap<String, String>,
+    /// 内嵌封面缩略图，JPEG data URL
+    pub cover: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaTrack {
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<usize>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveListing {
+    pub format: String,
+    pub entries: Vec<ArchiveEntry>,
+    /// tar 需要顺序读取整个文件才能得到总数，截断时为 None
+    pub total_entries: Option<usize>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// 生成文件预览。可能需要解析整个文件，应在 spawn_blocking 中调用
+pub fn preview(path: &str, options: &PreviewOptions) -> Result<FilePreview, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("无法获取文件元数据：{}", e))?
+        .len();
+    let header =
+        mime_detect::read_header_blocking(path).map_err(|e| format!("无法读取文件：{}", e))?;
+    let extension = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut mime_type = mime_detect::detect(&header, path, None, None, &HashMap::new());
+    // 带 XML 声明的 SVG 会被嗅探为 text/xml
+    if extension == "svg" {
+        mime_type = "image/svg+xml".to_string();
+    }
+
+    // 文件头确认是音视频时总是返回媒体预览，无法解析时信息为空。
+    // 只按扩展名推断的类型不可靠，例如 .ts 的 TypeScript 文件会被推断为 video/mp2t，
+    // 这类文件仅尝试解析音频，失败时按文本或二进制预览
+    let is_media = |mime: &str| mime.starts_with("audio/") || mime.starts_with("video/");
+    if is_media(&mime_type) {
+        if mime_detect::sniff(&header).is_some_and(is_media) {
+            return Ok(FilePreview {
+                content: PreviewContent::Media(media_info(path, &extension).unwrap_or_default()),
+                mime_type,
+                size,
+            });
+        }
+        if mime_type.starts_with("audio/") {
+            if let Ok(info) = media_info(path, &extension) {
+                return Ok(FilePreview {
+                    mime_type,
+                    size,
+                    content: PreviewContent::Media(info),
+                });
+            }
+        }
+    }
+
+    let content = match mime_type.as_str() {
+        t if THUMBNAIL_IMAGES.contains(&t) => {
+            // 只读取文件头获取尺寸
+            let dimensions = ImageReader::open(path)
+                .and_then(|reader| reader.with_guessed_format())
+                .ok()
+                .and_then(|reader| reader.into_dimensions().ok());
+            PreviewContent::Image {
+                url: thumbnail::thumbnail_url(path, options.max_dimension),
+                width: dimensions.map(|(width, _)| width),
+                height: dimensions.map(|(_, height)| height),
+            }
+        }
+        "image/svg+xml" => PreviewContent::Svg {
+            data_url: data_url(path, "image/svg+xml", size)?,
+        },
+        "application/pdf" => PreviewContent::Pdf(pdf_info(path, size)?),
+        "application/zip" => PreviewContent::Archive(list_zip(path)?),
+        "application/x-tar" => PreviewContent::Archive(list_tar(File::open(path), "tar")?),
+        "application/gzip" if path.ends_with(".tar.gz") || extension == "tgz" => {
+            let file = File::open(path).map(|file| GzDecoder::new(BufReader::new(file)));
+            PreviewContent::Archive(list_tar(file, "tar.gz")?)
+        }
+        t if is_text(t) || text_preview::is_text(&header) => {
+            PreviewContent::Text(text_preview::read_head(
+                path,
+                size,
+                options.max_lines.max(1),
+                options.max_bytes.clamp(1, MAX_INLINE_SIZE as usize),
+            )?)
+        }
+        _ => binary(&header),
+    };
+
+    Ok(FilePreview {
+        mime_type,
+        size,
+        content,
+    })
+}
+
+fn is_text(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || mime_type.ends_with("+json")
+        || mime_type.ends_with("+xml")
+        || matches!(
+            mime_type,
+            "application/json" | "application/xml" | "application/javascript" | "application/toml"
+        )
+}
+
+fn check_size(size: u64, limit: u64) -> Result<(), String> {
+    if size > limit {
+        return Err(format!("文件大小超过 {}MB 限制", limit / 1024 / 1024));
+    }
+    Ok(())
+}
+
+fn data_url(path: &str, mime_type: &str, size: u64) -> Result<String, String> {
+    check_size(size, MAX_INLINE_SIZE)?;
+    let data = std::fs::read(path).map_err(|e| format!("无法读取文件：{}", e))?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime_type,
+        general_purpose::STANDARD.encode(data)
+    ))
+}
+
+fn binary(header: &[u8]) -> PreviewContent {
+    PreviewContent::Binary {
+        hex_dump: hex_dump(&header[..header.len().min(HEX_DUMP_LEN)]),
+    }
+}
+
+// 每行 16 字节：`偏移  十六进制  |ASCII|`
+fn hex_dump(data: &[u8]) -> String {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = chunk
+                .iter()
+                .map(|b| match b {
+                    0x20..=0x7e => *b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            format!("{:08x}  {:<47}  |{}|", i * 16, hex, ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn pdf_info(path: &str, size: u64) -> Result<PdfInfo, String> {
+    check_size(size, MAX_PDF_SIZE)?;
+    let doc = Document::load(path).map_err(|e| format!("无法解析 PDF：{}", e))?;
+    let mut info = PdfInfo {
+        page_count: doc.get_pages().len(),
+        version: doc.version.clone(),
+        encrypted: doc.is_encrypted(),
+        ..Default::default()
+    };
+
+    // Info 可能是间接引用，也可能直接写在 trailer 中
+    let dict = match doc.trailer.get(b"Info") {
+        Ok(Object::Reference(id)) => doc.get_dictionary(*id).ok(),
+        Ok(object) => object.as_dict().ok(),
+        Err(_) => None,
+    };
+    if let Some(dict) = dict {
+        let text = |key: &[u8]| {
+            dict.get(key)
+                .ok()
+                .and_then(|object| lopdf::decode_text_string(object).ok())
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty())
+        };
+        info.title = text(b"Title");
+        info.author = text(b"Author");
+        info.creator = text(b"Creator");
+        info.producer = text(b"Producer");
+    }
+    Ok(info)
+}
+
+fn list_zip(path: &str) -> Result<ArchiveListing, String> {
+    let file = File::open(path).map_err(|e| format!("无法读取文件：{}", e))?;
+    let mut archive =
+        ZipArchive::new(BufReader::new(file)).map_err(|e| format!("无法解析 zip 文件：{}", e))?;
+    let total = archive.len();
+    let mut entries = Vec::with_capacity(total.min(MAX_ARCHIVE_ENTRIES));
+    for i in 0..total.min(MAX_ARCHIVE_ENTRIES) {
+        let entry = archive.by_index_raw(i).map_err(|e| e.to_string())?;
+        entries.push(ArchiveEntry {
+            name: entry.name().to_string(),
+            size: entry.size(),
+            is_dir: entry.is_dir(),
+        });
+    }
+    Ok(ArchiveListing {
+        format: "zip".to_string(),
+        entries,
+        total_entries: Some(total),
+        truncated: total > MAX_ARCHIVE_ENTRIES,
+    })
+}
+
+fn list_tar<R: Read>(reader: std::io::Result<R>, format: &str) -> Result<ArchiveListing, String> {
+    let reader = reader.map_err(|e| format!("无法读取文件：{}", e))?;
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    let mut truncated = false;
+    for entry in archive
+        .entries()
+        .map_err(|e| format!("无法解析 {} 文件：{}", format, e))?
+    {
+        if entries.len() == MAX_ARCHIVE_ENTRIES {
+            truncated = true;
+            break;
+        }
+        let entry = entry.map_err(|e| format!("无法解析 {} 文件：{}", format, e))?;
+        let header = entry.header();
+        entries.push(ArchiveEntry {
+            name: entry
+                .path()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            size: header.size().unwrap_or(0),
+            is_dir: header.entry_type().is_dir(),
+        });
+    }
+    Ok(ArchiveListing {
+        format: format.to_string(),
+        total_entries: (!truncated).then_some(entries.len()),
+        entries,
+        truncated,
+    })
+}
+
+fn media_info(path: &str, extension: &str) -> Result<MediaInfo, String> {
+    let file = File::open(path).map_err(|e| format!("无法读取文件：{}", e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("无法解析媒体文件：{}", e))?;
+
+    let mut info = MediaInfo {
+        duration_secs: probed
+            .format
+            .tracks()
+            .iter()
+            .filter_map(track_duration)
+            .reduce(f64::max),
+        tracks: probed.format.tracks().iter().map(media_track).collect(),
+        ..Default::default()
+    };
+
+    // 元数据可能在容器前的 ID3 标签中，也可能在容器内部；容器内部的优先
+    if let Some(metadata) = probed.metadata.get() {
+        if let Some(revision) = metadata.current() {
+            apply_metadata(&mut info, revision);
+        }
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        apply_metadata(&mut info, revision);
+    }
+    Ok(info)
+}
+
+fn track_duration(track: &Track) -> Option<f64> {
+    let params = &track.codec_params;
+    let time = params.time_base?.calc_time(params.n_frames?);
+    Some(time.seconds as f64 + time.frac)
+}
+
+fn media_track(track: &Track) -> MediaTrack {
+    let params = &track.codec_params;
+    let codec = symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map(|codec| codec.short_name.to_string())
+        .unwrap_or_else(|| params.codec.to_string());
+    MediaTrack {
+        codec,
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|channels| channels.count()),
+        language: track.language.clone(),
+    }
+}
+
+fn apply_metadata(info: &mut MediaInfo, revision: &MetadataRevision) {
+    for tag in revision.tags() {
+        let key = match tag.std_key {
+            Some(std_key) => format!("{:?}", std_key),
+            None => tag.key.clone(),
+        };
+        info.tags.insert(key, tag.value.to_string());
+    }
+    if let Some(cover) = revision.visuals().iter().find_map(|visual| {
+        let format = ImageFormat::from_mime_type(&visual.media_type)?;
+        cover_thumbnail(&visual.data, format)
+    }) {
+        info.cover = Some(cover);
+    }
+}
+
+fn cover_thumbnail(data: &[u8], format: ImageFormat) -> Option<String> {
+    let image = image::load_from_memory_with_format(data, format).ok()?;
+    // 不放大尺寸较小的封面
+    let image = if image.width().max(image.height()) > COVER_SIZE {
+        image.resize(COVER_SIZE, COVER_SIZE, FilterType::Triangle)
+    } else {
+        image
+    };
+    let jpeg = crate::image_pipeline::encode(&image, ImageFormat::Jpeg, 80).ok()?;
+    Some(format!(
+        "data:image/jpeg;base64,{}",
+        general_purpose::STANDARD.encode(jpeg)
+    ))
+}
@@ -0,0 +1,234 @@
+use image::metadata::Orientation;
+use img_parts::jpeg::{markers, Jpeg};
+use img_parts::png::Png;
+use img_parts::webp::{WebP, CHUNK_XMP};
+use img_parts::{Bytes, ImageEXIF};
+use lopdf::{Document, Object};
+use std::io::{Cursor, Read, Write};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+// 需要整体读入内存处理，超过该大小的文件不处理
+pub const MAX_SIZE: u64 = 32 * 1024 * 1024;
+
+// PNG 中可能包含作者、软件、GPS、XMP 等信息的文本与时间块
+const PNG_TEXT_CHUNKS: [[u8; 4]; 4] = [*b"tEXt", *b"zTXt", *b"iTXt", *b"tIME"];
+
+// PDF 文档信息中的作者与创建工具
+const PDF_INFO_KEYS: [&[u8]; 3] = [b"Author", b"Creator", b"Producer"];
+
+// Office Open XML 与 OpenDocument 中记录作者等信息的文件与元素
+const DOCUMENT_PROPS: [(&str, &[&str]); 3] = [
+    ("docProps/core.xml", &["dc:creator", "cp:lastModifiedBy"]),
+    ("docProps/app.xml", &["Company", "Manager"]),
+    (
+        "meta.xml",
+        &["dc:creator", "meta:initial-creator", "meta:printed-by"],
+    ),
+];
+
+enum Kind {
+    Jpeg,
+    Png,
+    WebP,
+    Pdf,
+    OfficeDocument,
+}
+
+fn kind_of(content_type: &str) -> Option<Kind> {
+    match content_type {
+        "image/jpeg" => Some(Kind::Jpeg),
+        "image/png" => Some(Kind::Png),
+        "image/webp" => Some(Kind::WebP),
+        "application/pdf" => Some(Kind::Pdf),
+        t if t.starts_with("application/vnd.openxmlformats-officedocument.")
+            || t.starts_with("application/vnd.oasis.opendocument.") =>
+        {
+            Some(Kind::OfficeDocument)
+        }
+        _ => None,
+    }
+}
+
+/// 判断指定类型的文件是否支持去除元数据
+pub fn applies_to(content_type: &str) -> bool {
+    kind_of(content_type).is_some()
+}
+
+/// 去除图片中的 EXIF/XMP/IPTC 以及 PDF、Office 文档中的作者等信息。
+/// 该函数可能比较耗时，应在 spawn_blocking 中调用。不支持的类型或没有可去除的元数据时返回 None
+pub fn strip(data: &[u8], content_type: &str) -> Result<Option<Vec<u8>>, String> {
+    let stripped = match kind_of(content_type) {
+        None => return Ok(None),
+        Some(Kind::Jpeg) => strip_jpeg(data)?,
+        Some(Kind::Png) => strip_png(data)?,
+        Some(Kind::WebP) => strip_webp(data)?,
+        Some(Kind::Pdf) => strip_pdf(data)?,
+        Some(Kind::OfficeDocument) => strip_office(data)?,
+    };
+    Ok(stripped.filter(|stripped| stripped.as_slice() != data))
+}
+
+// 只保留方向信息的最小 EXIF（大端 TIFF，仅含 Orientation 一项），
+// 否则去除 EXIF 后手机照片会显示为旋转的
+fn orientation_exif(exif: Option<Bytes>) -> Option<Bytes> {
+    let orientation = Orientation::from_exif_chunk(&exif?)?;
+    if orientation == Orientation::NoTransforms {
+        return None;
+    }
+    let mut tiff = Vec::with_capacity(26);
+    tiff.extend_from_slice(b"MM\x00\x2a");
+    tiff.extend_from_slice(&8u32.to_be_bytes());
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    // tag 0x0112 Orientation，类型 SHORT，数量 1
+    tiff.extend_from_slice(&0x0112u16.to_be_bytes());
+    tiff.extend_from_slice(&3u16.to_be_bytes());
+    tiff.extend_from_slice(&1u32.to_be_bytes());
+    tiff.extend_from_slice(&(orientation.to_exif() as u16).to_be_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+    Some(Bytes::from(tiff))
+}
+
+fn strip_jpeg(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(data))
+        .map_err(|e| format!("无法解析 JPEG：{}", e))?;
+    let exif = orientation_exif(jpeg.exif());
+    // APP1 包含 EXIF 与 XMP，APP13 包含 IPTC；保留 APP2 中的 ICC 色彩配置
+    for marker in [markers::APP1, markers::APP13, markers::COM] {
+        jpeg.remove_segments_by_marker(marker);
+    }
+    jpeg.set_exif(exif);
+    Ok(Some(jpeg.encoder().bytes().to_vec()))
+}
+
+fn strip_png(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut png = Png::from_bytes(Bytes::copy_from_slice(data))
+        .map_err(|e| format!("无法解析 PNG：{}", e))?;
+    let exif = orientation_exif(png.exif());
+    for chunk in PNG_TEXT_CHUNKS {
+        png.remove_chunks_by_type(chunk);
+    }
+    png.set_exif(exif);
+    Ok(Some(png.encoder().bytes().to_vec()))
+}
+
+fn strip_webp(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut webp = WebP::from_bytes(Bytes::copy_from_slice(data))
+        .map_err(|e| format!("无法解析 WebP：{}", e))?;
+    let exif = orientation_exif(webp.exif());
+    webp.remove_chunks_by_id(CHUNK_XMP);
+    webp.set_exif(exif);
+    Ok(Some(webp.encoder().bytes().to_vec()))
+}
+
+fn strip_pdf(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut doc = Document::load_mem(data).map_err(|e| format!("无法解析 PDF：{}", e))?;
+    // 加密的 PDF 无法修改，保持原样
+    if doc.is_encrypted() {
+        return Ok(None);
+    }
+
+    // Info 可能是间接引用，也可能直接写在 trailer 中
+    let info = match doc.trailer.get(b"Info").and_then(Object::as_reference) {
+        Ok(id) => doc.get_object_mut(id).and_then(Object::as_dict_mut).ok(),
+        Err(_) => doc
+            .trailer
+            .get_mut(b"Info")
+            .and_then(Object::as_dict_mut)
+            .ok(),
+    };
+    if let Some(info) = info {
+        for key in PDF_INFO_KEYS {
+            info.remove(key);
+        }
+    }
+    // 文档级 XMP 元数据中同样包含作者与创建工具
+    if let Ok(catalog) = doc.catalog_mut() {
+        catalog.remove(b"Metadata");
+    }
+    doc.prune_objects();
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out)
+        .map_err(|e| format!("无法写入 PDF：{}", e))?;
+    Ok(Some(out))
+}
+
+fn strip_office(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|e| format!("无法解析文档：{}", e))?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut changed = false;
+
+    // 按原顺序复制，OpenDocument 要求 mimetype 为第一个文件
+    for i in 0..archive.len() {
+        let elements = archive
+            .name_for_index(i)
+            .and_then(|name| DOCUMENT_PROPS.iter().find(|(path, _)| *path == name))
+            .map(|(_, elements)| *elements);
+        let Some(elements) = elements else {
+            let entry = archive.by_index_raw(i).map_err(|e| e.to_string())?;
+            writer.raw_copy_file(entry).map_err(|e| e.to_string())?;
+            continue;
+        };
+
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        let mut xml = String::new();
+        entry
+            .read_to_string(&mut xml)
+            .map_err(|e| format!("无法读取文档属性：{}", e))?;
+        let original_len = xml.len();
+        for element in elements {
+            xml = remove_element(&xml, element);
+        }
+        changed |= xml.len() != original_len;
+        let options = SimpleFileOptions::default().compression_method(entry.compression());
+        writer
+            .start_file(entry.name().to_string(), options)
+            .map_err(|e| e.to_string())?;
+        writer
+            .write_all(xml.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+
+    // 重新打包后的字节总会不同，只有确实删除了元素时才使用
+    if !changed {
+        return Ok(None);
+    }
+    let out = writer.finish().map_err(|e| e.to_string())?;
+    Ok(Some(out.into_inner()))
+}
+
+// 删除 XML 中所有指定名称的元素，包括自闭合形式
+fn remove_element(xml: &str, name: &str) -> String {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut out = String::with_capacity(xml.len());
+    let mut rest = xml;
+
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // 确认是完整的元素名，而不是以其为前缀的其他元素
+        if !after.starts_with(['>', '/', ' ', '\t', '\r', '\n']) {
+            out.push_str(&rest[..start + open.len()]);
+            rest = after;
+            continue;
+        }
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        let end = if after[..tag_end].ends_with('/') {
+            Some(tag_end + 1)
+        } else {
+            after.find(&close).map(|i| i + close.len())
+        };
+        let Some(end) = end else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        rest = &after[end..];
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,502 @@
+use crate::compression::CompressionRules;
+use crate::crypto;
+use crate::encryption::EncryptionConfig;
+use crate::hooks::HookSettings;
+use crate::ignore_rules::ScanRules;
+use crate::image_pipeline::{ImageOptimization, VariantRule};
+use crate::key_rules::KeyRules;
+use crate::link_format::LinkSettings;
+use crate::typ::ObjectHeaders;
+use base64::{engine::general_purpose, Engine};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+const STORE_FILENAME: &str = "profiles.json";
+const STORE_VERSION: u32 = 1;
+// 用于校验主密钥是否正确
+const VERIFIER_PLAINTEXT: &[u8] = b"r2uploader-profile-store";
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+const KEYRING_SERVICE: &str = "R2Uploader";
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+const KEYRING_USER: &str = "profile-store";
+
+static PROFILE_STORE: Lazy<RwLock<ProfileStore>> =
+    Lazy::new(|| RwLock::new(ProfileStore::default()));
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Provider {
+    #[default]
+    R2,
+    S3,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketProfile {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub provider: Provider,
+    pub bucket_name: String,
+    #[serde(default)]
+    pub account_id: String,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub access_key: String,
+    #[serde(default)]
+    pub secret_key: String,
+    // 默认的 key 模板，如 `{yyyy}/{mm}/{dd}/{filename}-{random:6}.{ext}`
+    #[serde(default)]
+    pub key_template: Option<String>,
+    #[serde(default)]
+    pub key_rules: KeyRules,
+    #[serde(default)]
+    pub default_headers: ObjectHeaders,
+    // 扩展名（不含 `.`）到 MIME 类型的覆盖表
+    #[serde(default)]
+    pub mime_overrides: HashMap<String, String>,
+    #[serde(default)]
+    pub compression: CompressionRules,
+    // 客户端加密配置，passphrase 与密钥一样加密保存
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    // SSE-C 客户提供的密钥（base64 编码的 256 位密钥）
+    #[serde(default)]
+    pub sse_customer_key: Option<String>,
+    // 更新配置时 sse_customer_key 为空表示保留原有的密钥，设置此项才会清除
+    #[serde(default, skip_serializing)]
+    pub clear_sse_customer_key: bool,
+    // 上传前去除图片 EXIF/XMP/IPTC 与文档中的作者信息
+    #[serde(default)]
+    pub strip_metadata: bool,
+    #[serde(default)]
+    pub image_optimization: ImageOptimization,
+    // 随原图一起上传的缩略图、`@2x` 等变体
+    #[serde(default)]
+    pub image_variants: Vec<VariantRule>,
+    // 扫描文件夹时默认排除的文件
+    #[serde(default)]
+    pub scan_rules: ScanRules,
+    // 上传成功后生成的链接格式与自定义模板
+    #[serde(default)]
+    pub links: LinkSettings,
+    // 从远程 URL 上传时的大小上限（字节），为空时使用默认值
+    #[serde(default)]
+    pub max_remote_size: Option<u64>,
+    // 上传成功或失败后触发的 webhook 与本地命令
+    #[serde(default)]
+    pub hooks: HookSettings,
+}
+
+impl BucketProfile {
+    pub fn endpoint_url(&self) -> String {
+        match (&self.endpoint, self.provider) {
+            (Some(endpoint), _) if !endpoint.is_empty() => endpoint.clone(),
+            (_, Provider::R2) => format!("https://{}.r2.cloudflarestorage.com", self.account_id),
+            (_, Provider::S3) => format!("https://s3.{}.amazonaws.com", self.region_name()),
+        }
+    }
+
+    pub fn region_name(&self) -> String {
+        match (&self.region, self.provider) {
+            (Some(region), _) if !region.is_empty() => region.clone(),
+            (_, Provider::R2) => "auto".to_string(),
+            (_, Provider::S3) => "us-east-1".to_string(),
+        }
+    }
+
+    // 返回给前端时隐藏 secret、加密口令、SSE-C 密钥和 webhook 签名密钥
+    pub(crate) fn redacted(&self) -> Self {
+        let mut hooks = self.hooks.clone();
+        for webhook in &mut hooks.webhooks {
+            webhook.secret = None;
+        }
+        Self {
+            secret_key: String::new(),
+            encryption: EncryptionConfig {
+                passphrase: None,
+                ..self.encryption.clone()
+            },
+            sse_customer_key: None,
+            hooks,
+            ..self.clone()
+        }
+    }
+
+    // 对所有敏感字段应用 f，用于落盘前加密和读取后解密
+    fn map_secrets(mut self, f: impl Fn(&str) -> Result<String, String>) -> Result<Self, String> {
+        self.access_key = f(&self.access_key)?;
+        self.secret_key = f(&self.secret_key)?;
+        self.encryption.passphrase = self.encryption.passphrase.as_deref().map(&f).transpose()?;
+        self.sse_customer_key = self.sse_customer_key.as_deref().map(&f).transpose()?;
+        for webhook in &mut self.hooks.webhooks {
+            webhook.secret = webhook.secret.as_deref().map(&f).transpose()?;
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum KeySource {
+    Passphrase,
+    Keyring,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreFile {
+    version: u32,
+    key_source: KeySource,
+    salt: String,
+    verifier: String,
+    // access_key 与 secret_key 均为密文
+    profiles: Vec<BucketProfile>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileStoreStatus {
+    pub initialized: bool,
+    pub unlocked: bool,
+    pub key_source: Option<KeySource>,
+    pub keyring_available: bool,
+}
+
+#[derive(Default)]
+struct ProfileStore {
+    path: Option<PathBuf>,
+    key: Option<[u8; crypto::KEY_LEN]>,
+    key_source: Option<KeySource>,
+    salt: Vec<u8>,
+    // 解锁后的明文 profile，仅保存在内存中
+    profiles: Vec<BucketProfile>,
+}
+
+impl ProfileStore {
+    fn key(&self) -> Result<&[u8; crypto::KEY_LEN], String> {
+        self.key
+            .as_ref()
+            .ok_or_else(|| "配置存储尚未解锁".to_string())
+    }
+
+    async fn save(&self) -> Result<(), String> {
+        self.save_profiles(&self.profiles).await
+    }
+
+    // 保存指定的配置列表，调用方在写入成功后再更新内存中的配置
+    async fn save_profiles(&self, profiles: &[BucketProfile]) -> Result<(), String> {
+        let key = self.key()?;
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| "配置存储尚未初始化".to_string())?;
+
+        let profiles = profiles
+            .iter()
+            .map(|profile| {
+                profile
+                    .clone()
+                    .map_secrets(|secret| crypto::seal(key, secret.as_bytes()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let file = StoreFile {
+            version: STORE_VERSION,
+            key_source: self.key_source.unwrap_or(KeySource::Passphrase),
+            salt: general_purpose::STANDARD.encode(&self.salt),
+            verifier: crypto::seal(key, VERIFIER_PLAINTEXT)?,
+            profiles,
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("无法创建配置目录：{}", e))?;
+        }
+        // 先写临时文件再替换，避免写入中断导致配置损坏
+        let tmp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, json)
+            .await
+            .map_err(|e| format!("无法写入配置文件：{}", e))?;
+        tokio::fs::rename(&tmp_path, path)
+            .await
+            .map_err(|e| format!("无法写入配置文件：{}", e))
+    }
+}
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(STORE_FILENAME))
+        .map_err(|e| format!("无法获取应用数据目录：{}", e))
+}
+
+async fn read_store_file(path: &PathBuf) -> Result<Option<StoreFile>, String> {
+    match tokio::fs::read(path).await {
+        Ok(data) => serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| format!("配置文件格式错误：{}", e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("无法读取配置文件：{}", e)),
+    }
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+fn keyring_key(create: bool) -> Result<[u8; crypto::KEY_LEN], String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| format!("无法访问系统钥匙串：{}", e))?;
+    match entry.get_password() {
+        Ok(encoded) => general_purpose::STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| "系统钥匙串中的密钥格式错误".to_string()),
+        Err(keyring::Error::NoEntry) if create => {
+            let key = crypto::random_bytes::<{ crypto::KEY_LEN }>();
+            entry
+                .set_password(&general_purpose::STANDARD.encode(key))
+                .map_err(|e| format!("无法写入系统钥匙串：{}", e))?;
+            Ok(key)
+        }
+        Err(e) => Err(format!("无法读取系统钥匙串：{}", e)),
+    }
+}
+
+#[cfg(any(target_os = "ios", target_os = "android"))]
+fn keyring_key(_create: bool) -> Result<[u8; crypto::KEY_LEN], String> {
+    Err("当前平台不支持系统钥匙串".to_string())
+}
+
+fn keyring_available() -> bool {
+    #[cfg(any(target_os = "ios", target_os = "android"))]
+    return false;
+
+    #[cfg(not(any(target_os = "ios", target_os = "android")))]
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).is_ok()
+}
+
+/// 按 id 获取已解锁的 profile（包含明文密钥），供上传命令内部使用
+pub async fn get_profile(profile_id: &str) -> Result<BucketProfile, String> {
+    let store = PROFILE_STORE.read().await;
+    store.key()?;
+    store
+        .profiles
+        .iter()
+        .find(|p| p.id == profile_id)
+        .cloned()
+        .ok_or_else(|| format!("找不到存储桶配置：{}", profile_id))
+}
+
+/// 批量添加 profile，返回隐藏 secret 后的结果
+pub async fn add_profiles(profiles: Vec<BucketProfile>) -> Result<Vec<BucketProfile>, String> {
+    let mut store = PROFILE_STORE.write().await;
+    store.key()?;
+    let mut result = Vec::with_capacity(profiles.len());
+    for mut profile in profiles {
+        if profile.bucket_name.trim().is_empty() {
+            return Err(format!("存储桶配置 {} 缺少存储桶名称", profile.name));
+        }
+        profile.id = Uuid::new_v4().to_string();
+        result.push(profile.redacted());
+        store.profiles.push(profile);
+    }
+    store.save().await?;
+    Ok(result)
+}
+
+/// 是否已有名称与 endpoint 都相同的 profile，用于导入时去重
+pub async fn contains(profile: &BucketProfile) -> Result<bool, String> {
+    let store = PROFILE_STORE.read().await;
+    store.key()?;
+    let endpoint = profile.endpoint_url();
+    Ok(store
+        .profiles
+        .iter()
+        .any(|p| p.name == profile.name && p.endpoint_url() == endpoint))
+}
+
+/// 按 id 批量获取 profile，保持传入顺序
+pub async fn get_profiles(profile_ids: &[String]) -> Result<Vec<BucketProfile>, String> {
+    let mut result = Vec::with_capacity(profile_ids.len());
+    for id in profile_ids {
+        result.push(get_profile(id).await?);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn profile_status(app: AppHandle) -> Result<ProfileStoreStatus, String> {
+    let path = store_path(&app)?;
+    let file = read_store_file(&path).await?;
+    let store = PROFILE_STORE.read().await;
+    Ok(ProfileStoreStatus {
+        initialized: file.is_some(),
+        unlocked: store.key.is_some(),
+        key_source: file.map(|f| f.key_source),
+        keyring_available: keyring_available(),
+    })
+}
+
+/// 解锁配置存储。传入 passphrase 时使用主口令，否则使用系统钥匙串。
+/// 若配置文件不存在，则以所选方式初始化一个新的存储。
+#[tauri::command]
+pub async fn profile_unlock(app: AppHandle, passphrase: Option<String>) -> Result<(), String> {
+    let path = store_path(&app)?;
+    let file = read_store_file(&path).await?;
+    let mut store = PROFILE_STORE.write().await;
+
+    let Some(file) = file else {
+        let (key, key_source, salt) = match passphrase {
+            Some(passphrase) => {
+                let salt = crypto::random_bytes::<{ crypto::SALT_LEN }>().to_vec();
+                (
+                    crypto::derive_key(passphrase.as_bytes(), &salt)?,
+                    KeySource::Passphrase,
+                    salt,
+                )
+            }
+            None => (keyring_key(true)?, KeySource::Keyring, Vec::new()),
+        };
+        *store = ProfileStore {
+            path: Some(path),
+            key: Some(key),
+            key_source: Some(key_source),
+            salt,
+            profiles: Vec::new(),
+        };
+        return store.save().await;
+    };
+
+    let salt = general_purpose::STANDARD
+        .decode(&file.salt)
+        .map_err(|e| format!("配置文件格式错误：{}", e))?;
+    let key = match (file.key_source, passphrase) {
+        (KeySource::Passphrase, Some(passphrase)) => {
+            crypto::derive_key(passphrase.as_bytes(), &salt)?
+        }
+        (KeySource::Passphrase, None) => return Err("需要输入主口令".to_string()),
+        (KeySource::Keyring, _) => keyring_key(false)?,
+    };
+    crypto::open(&key, &file.verifier).map_err(|_| "主口令错误".to_string())?;
+
+    let mut profiles = Vec::with_capacity(file.profiles.len());
+    for profile in file.profiles {
+        profiles.push(profile.map_secrets(|sealed| {
+            String::from_utf8(crypto::open(&key, sealed)?).map_err(|e| e.to_string())
+        })?);
+    }
+
+    *store = ProfileStore {
+        path: Some(path),
+        key: Some(key),
+        key_source: Some(file.key_source),
+        salt,
+        profiles,
+    };
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn profile_lock() -> Result<(), String> {
+    *PROFILE_STORE.write().await = ProfileStore::default();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn profile_list() -> Result<Vec<BucketProfile>, String> {
+    let store = PROFILE_STORE.read().await;
+    store.key()?;
+    Ok(store.profiles.iter().map(BucketProfile::redacted).collect())
+}
+
+#[tauri::command]
+pub async fn profile_add(profile: BucketProfile) -> Result<BucketProfile, String> {
+    add_profiles(vec![profile])
+        .await?
+        .pop()
+        .ok_or_else(|| "添加存储桶配置失败".to_string())
+}
+
+/// 更新 profile。secret_key、加密口令、SSE-C 密钥或 webhook 签名密钥为空时保留原有的值；
+/// 设置 encryption.clearPassphrase、clearSseCustomerKey 或 webhook 的 clearSecret 时清除对应的值
+#[tauri::command]
+pub async fn profile_update(profile: BucketProfile) -> Result<BucketProfile, String> {
+    let mut store = PROFILE_STORE.write().await;
+    store.key()?;
+    let index = store
+        .profiles
+        .iter()
+        .position(|p| p.id == profile.id)
+        .ok_or_else(|| format!("找不到存储桶配置：{}", profile.id))?;
+    // 在副本上合并，保存成功后才替换内存中的配置
+    let existing = &store.profiles[index];
+    let secret_key = if profile.secret_key.is_empty() {
+        existing.secret_key.clone()
+    } else {
+        profile.secret_key.clone()
+    };
+    let passphrase = match &profile.encryption.passphrase {
+        _ if profile.encryption.clear_passphrase => None,
+        Some(passphrase) if !passphrase.is_empty() => Some(passphrase.clone()),
+        _ => existing.encryption.passphrase.clone(),
+    };
+    let sse_customer_key = match &profile.sse_customer_key {
+        _ if profile.clear_sse_customer_key => None,
+        Some(key) if !key.is_empty() => Some(key.clone()),
+        _ => existing.sse_customer_key.clone(),
+    };
+    // webhook 签名密钥按 URL 对应
+    let mut hooks = profile.hooks.clone();
+    for webhook in &mut hooks.webhooks {
+        if std::mem::take(&mut webhook.clear_secret) {
+            webhook.secret = None;
+        } else if webhook.secret.as_deref().unwrap_or_default().is_empty() {
+            webhook.secret = existing
+                .hooks
+                .webhooks
+                .iter()
+                .find(|old| old.url == webhook.url)
+                .and_then(|old| old.secret.clone());
+        }
+    }
+    let updated = BucketProfile {
+        secret_key,
+        hooks,
+        sse_customer_key,
+        encryption: EncryptionConfig {
+            passphrase,
+            clear_passphrase: false,
+            ..profile.encryption.clone()
+        },
+        clear_sse_customer_key: false,
+        ..profile
+    };
+    let result = updated.redacted();
+    let mut profiles = store.profiles.clone();
+    profiles[index] = updated;
+    store.save_profiles(&profiles).await?;
+    store.profiles = profiles;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn profile_remove(profile_id: String) -> Result<(), String> {
+    let mut store = PROFILE_STORE.write().await;
+    store.key()?;
+    store.profiles.retain(|p| p.id != profile_id);
+    store.save().await
+}
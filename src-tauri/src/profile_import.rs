@@ -0,0 +1,462 @@
+use crate::profile::{self, BucketProfile, Provider};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+// 等待用户确认的导入结果，键是返回给前端的 token。密钥只保存在这里，不发送给前端
+static PENDING_IMPORTS: Lazy<DashMap<String, Vec<BucketProfile>>> = Lazy::new(DashMap::new);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportSource {
+    Rclone,
+    Aws,
+}
+
+/// 解析出的 profile，隐藏了 access key 与 secret key。id 仅用于 profile_import_commit 时选择
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    pub token: String,
+    pub profiles: Vec<BucketProfile>,
+}
+
+// 简单的 INI 解析结果：节名 -> (键 -> 值)，保持文件中的顺序
+type IniSections = Vec<(String, BTreeMap<String, String>)>;
+
+fn parse_ini(content: &str) -> IniSections {
+    let mut sections: IniSections = Vec::new();
+    // AWS config 中的嵌套配置（如 `s3 =` 后缩进的键）以 `s3.endpoint_url` 的形式保存
+    let mut nested: Option<String> = None;
+
+    for raw_line in content.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push((line[1..line.len() - 1].trim().to_string(), BTreeMap::new()));
+            nested = None;
+            continue;
+        }
+
+        let Some((_, values)) = sections.last_mut() else {
+            continue;
+        };
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_string();
+
+        let indented = raw_line.starts_with(|c: char| c.is_whitespace());
+        match &nested {
+            Some(parent) if indented => {
+                values.insert(format!("{}.{}", parent, key), value);
+            }
+            _ if value.is_empty() => nested = Some(key),
+            _ => {
+                nested = None;
+                values.insert(key, value);
+            }
+        }
+    }
+
+    sections
+}
+
+fn non_empty(value: Option<&String>) -> Option<String> {
+    value.filter(|v| !v.is_empty()).cloned()
+}
+
+// 从 R2 endpoint 中提取 account id，如 https://<account_id>.r2.cloudflarestorage.com
+fn r2_account_id(endpoint: &str) -> Option<String> {
+    let host = endpoint
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()?;
+    host.strip_suffix(".r2.cloudflarestorage.com")
+        .filter(|id| !id.is_empty() && !id.contains('.'))
+        .map(|id| id.to_string())
+}
+
+fn build_profile(
+    name: &str,
+    endpoint: Option<String>,
+    region: Option<String>,
+    access_key: String,
+    secret_key: String,
+    cloudflare: bool,
+) -> BucketProfile {
+    let account_id = endpoint.as_deref().and_then(r2_account_id);
+    let provider = if cloudflare || account_id.is_some() {
+        Provider::R2
+    } else {
+        Provider::S3
+    };
+
+    BucketProfile {
+        name: name.to_string(),
+        provider,
+        // 标准 R2 endpoint 可由 account id 推出，无需单独保存
+        endpoint: if account_id.is_some() { None } else { endpoint },
+        account_id: account_id.unwrap_or_default(),
+        region,
+        access_key,
+        secret_key,
+        ..Default::default()
+    }
+}
+
+/// 解析 rclone.conf，返回其中 type = s3 的 remote
+pub fn parse_rclone(content: &str) -> Vec<BucketProfile> {
+    parse_ini(content)
+        .into_iter()
+        .filter(|(_, values)| values.get("type").map(String::as_str) == Some("s3"))
+        .filter_map(|(name, values)| {
+            let access_key = non_empty(values.get("access_key_id"))?;
+            let secret_key = non_empty(values.get("secret_access_key"))?;
+            let cloudflare = values
+                .get("provider")
+                .is_some_and(|p| p.eq_ignore_ascii_case("cloudflare"));
+            Some(build_profile(
+                &name,
+                non_empty(values.get("endpoint")),
+                non_empty(values.get("region")),
+                access_key,
+                secret_key,
+                cloudflare,
+            ))
+        })
+        .collect()
+}
+
+/// 解析 AWS CLI 的 credentials 与 config 文件，按 profile 名合并
+pub fn parse_aws(credentials: &str, config: &str) -> Vec<BucketProfile> {
+    let config: BTreeMap<String, BTreeMap<String, String>> = parse_ini(config)
+        .into_iter()
+        .map(|(name, values)| {
+            // config 文件中除 default 外的节名为 `profile <name>`
+            let name = name
+                .strip_prefix("profile ")
+                .map(|n| n.trim().to_string())
+                .unwrap_or(name);
+            (name, values)
+        })
+        .collect();
+
+    parse_ini(credentials)
+        .into_iter()
+        .filter_map(|(name, values)| {
+            let access_key = non_empty(values.get("aws_access_key_id"))?;
+            let secret_key = non_empty(values.get("aws_secret_access_key"))?;
+            let settings = config.get(&name);
+            let setting =
+                |key: &str| non_empty(values.get(key)).or_else(|| non_empty(settings?.get(key)));
+            let endpoint = setting("s3.endpoint_url").or_else(|| setting("endpoint_url"));
+            Some(build_profile(
+                &name,
+                endpoint,
+                setting("region"),
+                access_key,
+                secret_key,
+                false,
+            ))
+        })
+        .collect()
+}
+
+/// 导出为 rclone.conf 格式。include_secrets 为 false 时不写入密钥
+pub fn to_rclone(profiles: &[BucketProfile], include_secrets: bool) -> String {
+    let mut out = String::new();
+    for p in profiles {
+        out.push_str(&format!("[{}]\n", p.name.replace(['[', ']'], "")));
+        out.push_str("type = s3\n");
+        out.push_str(match p.provider {
+            Provider::R2 => "provider = Cloudflare\n",
+            Provider::S3 => "provider = Other\n",
+        });
+        if include_secrets {
+            out.push_str(&format!("access_key_id = {}\n", p.access_key));
+            out.push_str(&format!("secret_access_key = {}\n", p.secret_key));
+        } else {
+            out.push_str("env_auth = true\n");
+        }
+        out.push_str(&format!("region = {}\n", p.region_name()));
+        out.push_str(&format!("endpoint = {}\n", p.endpoint_url()));
+        out.push('\n');
+    }
+    out
+}
+
+fn home_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .home_dir()
+        .map_err(|e| format!("无法获取用户目录：{}", e))
+}
+
+fn default_rclone_path(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Ok(path) = std::env::var("RCLONE_CONFIG") {
+        return Ok(PathBuf::from(path));
+    }
+    let path = home_dir(app)?
+        .join(".config")
+        .join("rclone")
+        .join("rclone.conf");
+    if path.exists() {
+        return Ok(path);
+    }
+    // Windows 上 rclone 默认使用 %APPDATA%\rclone
+    app.path()
+        .config_dir()
+        .map(|dir| dir.join("rclone").join("rclone.conf"))
+        .map_err(|e| format!("无法获取配置目录：{}", e))
+}
+
+fn default_aws_paths(app: &AppHandle) -> Result<(PathBuf, PathBuf), String> {
+    let aws_dir = home_dir(app)?.join(".aws");
+    let credentials = std::env::var("AWS_SHARED_CREDENTIALS_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| aws_dir.join("credentials"));
+    let config = std::env::var("AWS_CONFIG_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| aws_dir.join("config"));
+    Ok((credentials, config))
+}
+
+async fn read_optional(path: &PathBuf) -> Result<String, String> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(format!("无法读取文件 {}：{}", path.display(), e)),
+    }
+}
+
+/// 从 rclone 或 AWS CLI 配置文件解析 profile，不保存。解析结果保存在后端，
+/// 返回隐藏密钥的副本，由用户选择并补充 bucket 名称后通过 profile_import_commit 保存。
+/// path 为空时使用各工具的默认路径；已保存过的 profile（名称与 endpoint 都相同）不再返回。
+#[tauri::command]
+pub async fn profile_import(
+    app: AppHandle,
+    source: ImportSource,
+    path: Option<String>,
+) -> Result<ImportPreview, String> {
+    let profiles = match source {
+        ImportSource::Rclone => {
+            let path = match path {
+                Some(path) => PathBuf::from(path),
+                None => default_rclone_path(&app)?,
+            };
+            parse_rclone(&read_optional(&path).await?)
+        }
+        ImportSource::Aws => {
+            let (credentials, config) = match path {
+                Some(path) => {
+                    let credentials = PathBuf::from(path);
+                    let config = credentials.with_file_name("config");
+                    (credentials, config)
+                }
+                None => default_aws_paths(&app)?,
+            };
+            parse_aws(
+                &read_optional(&credentials).await?,
+                &read_optional(&config).await?,
+            )
+        }
+    };
+
+    if profiles.is_empty() {
+        return Err("没有找到可导入的 S3/R2 配置".to_string());
+    }
+    let mut pending = Vec::with_capacity(profiles.len());
+    for mut profile in profiles {
+        if !profile::contains(&profile).await? {
+            profile.id = Uuid::new_v4().to_string();
+            pending.push(profile);
+        }
+    }
+
+    let preview = pending
+        .iter()
+        .map(|profile| BucketProfile {
+            access_key: String::new(),
+            ..profile.redacted()
+        })
+        .collect();
+    let token = Uuid::new_v4().to_string();
+    PENDING_IMPORTS.insert(token.clone(), pending);
+    Ok(ImportPreview {
+        token,
+        profiles: preview,
+    })
+}
+
+/// 保存 profile_import 返回的 profile。ids 为要导入的 profile，bucket_names 按顺序对应各自的存储桶名称。
+/// 保存成功后 token 失效，返回隐藏密钥后的 profile
+#[tauri::command]
+pub async fn profile_import_commit(
+    token: String,
+    ids: Vec<String>,
+    bucket_names: Vec<String>,
+) -> Result<Vec<BucketProfile>, String> {
+    if ids.len() != bucket_names.len() {
+        return Err("导入的配置与存储桶名称数量不一致".to_string());
+    }
+    let profiles = {
+        let pending = PENDING_IMPORTS
+            .get(&token)
+            .ok_or_else(|| "导入已过期，请重新导入".to_string())?;
+        ids.iter()
+            .zip(bucket_names)
+            .map(|(id, bucket_name)| {
+                let profile = pending
+                    .iter()
+                    .find(|p| &p.id == id)
+                    .ok_or_else(|| format!("找不到导入的配置：{}", id))?;
+                Ok(BucketProfile {
+                    bucket_name,
+                    ..profile.clone()
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
+    // 缺少存储桶名称等错误时保留 token，用户修改后可以再次提交
+    let result = profile::add_profiles(profiles).await?;
+    PENDING_IMPORTS.remove(&token);
+    Ok(result)
+}
+
+/// 导出为 rclone.conf 格式并写入用户选择的文件，返回导出的 profile 数。
+/// 包含密钥的内容不经过前端
+#[tauri::command]
+pub async fn profile_export_rclone(
+    profile_ids: Vec<String>,
+    include_secrets: bool,
+    path: String,
+) -> Result<usize, String> {
+    let profiles = profile::get_profiles(&profile_ids).await?;
+    tokio::fs::write(&path, to_rclone(&profiles, include_secrets))
+        .await
+        .map_err(|e| format!("无法写入导出文件：{}", e))?;
+    Ok(profiles.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rclone_remotes() {
+        let content = "\
+# comment
+[r2]
+type = s3
+provider = Cloudflare
+access_key_id = AK
+secret_access_key = SK
+endpoint = https://abc123.r2.cloudflarestorage.com
+
+[minio]
+type = s3
+provider = Minio
+access_key_id = AK2
+secret_access_key = SK2
+endpoint = http://localhost:9000
+region = local
+
+[missing-secret]
+type = s3
+access_key_id = AK3
+
+[drive]
+type = drive
+";
+        let profiles = parse_rclone(content);
+        assert_eq!(profiles.len(), 2);
+
+        let r2 = &profiles[0];
+        assert_eq!(r2.name, "r2");
+        assert_eq!(r2.provider, Provider::R2);
+        assert_eq!(r2.account_id, "abc123");
+        assert_eq!(r2.endpoint, None);
+        assert_eq!(
+            (r2.access_key.as_str(), r2.secret_key.as_str()),
+            ("AK", "SK")
+        );
+
+        let minio = &profiles[1];
+        assert_eq!(minio.provider, Provider::S3);
+        assert_eq!(minio.endpoint.as_deref(), Some("http://localhost:9000"));
+        assert_eq!(minio.region.as_deref(), Some("local"));
+    }
+
+    #[test]
+    fn aws_credentials_and_config() {
+        let credentials = "\
+[default]
+aws_access_key_id = AK1
+aws_secret_access_key = SK1
+
+[work]
+aws_access_key_id = AK2
+aws_secret_access_key = SK2
+region = eu-west-1
+
+[config-only-keys]
+region = us-west-2
+";
+        let config = "\
+[default]
+region = us-east-2
+
+[profile work]
+region = ap-south-1
+s3 =
+  endpoint_url = https://xyz789.r2.cloudflarestorage.com
+
+[profile config-only-keys]
+aws_access_key_id = AK3
+aws_secret_access_key = SK3
+";
+        let profiles = parse_aws(credentials, config);
+        // 密钥只从 credentials 读取，只在 config 中出现的 profile 不导入
+        assert_eq!(profiles.len(), 2);
+
+        let default = &profiles[0];
+        assert_eq!(default.name, "default");
+        assert_eq!(default.provider, Provider::S3);
+        assert_eq!(default.region.as_deref(), Some("us-east-2"));
+        assert_eq!(default.endpoint, None);
+
+        // `[profile work]` 与 credentials 中的 `[work]` 合并，credentials 中的值优先，
+        // endpoint 只在 config 的嵌套 s3 配置中
+        let work = &profiles[1];
+        assert_eq!(work.name, "work");
+        assert_eq!(work.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(work.provider, Provider::R2);
+        assert_eq!(work.account_id, "xyz789");
+        assert_eq!(
+            (work.access_key.as_str(), work.secret_key.as_str()),
+            ("AK2", "SK2")
+        );
+    }
+
+    #[test]
+    fn aws_top_level_endpoint() {
+        let credentials = "[dev]\naws_access_key_id = AK\naws_secret_access_key = SK\n";
+        let config = "[profile dev]\nendpoint_url = http://localhost:9000\n";
+        let profiles = parse_aws(credentials, config);
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].provider, Provider::S3);
+        assert_eq!(
+            profiles[0].endpoint.as_deref(),
+            Some("http://localhost:9000")
+        );
+    }
+}
@@ -1,24 +1,86 @@
-use crate::typ::{File, UploadHistory, UploadSource, UploadStatus};
+use crate::batch;
+use crate::compression;
+use crate::encryption::{self, EncryptionConfig, ObjectDecryptor};
+use crate::history::{self, HistoryStatus, UploadFinished, UploadStarted};
+use crate::hooks::{self, HookError, HookEvent, HookPayload};
+use crate::key_rules;
+use crate::link_format::LinkContext;
+use crate::pipeline::{self, PreparedVariant, UploadBody};
+use crate::profile::{self, BucketProfile};
+use crate::sse::{ApplyCopySourceSseCustomerKey, ApplySseCustomerKey, SseCustomerKey};
+use crate::typ::{
+    File, ObjectInfo, UploadDetails, UploadHistory, UploadRetry, UploadStatus, VariantLink,
+};
+use crate::upload_options::{self, ApplyUploadOptions, UploadOptions};
 use aws_config::timeout::TimeoutConfig;
 use aws_config::ConfigLoader;
+use aws_sdk_s3::config::http::HttpResponse;
 use aws_sdk_s3::config::{Credentials, Region};
+use aws_sdk_s3::error::SdkError;
 use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
 use aws_sdk_s3::Client;
 use aws_smithy_runtime::client::http::hyper_014::HyperClientBuilder;
 use dashmap::DashMap;
 use hyper::client::HttpConnector;
 use hyper_proxy::ProxyConnector;
-use mime_guess::from_path;
 use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 use tauri::{AppHandle, Emitter};
-use tokio::io::AsyncReadExt;
-use tokio::sync::Semaphore;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{oneshot, Semaphore};
+
+// 遇到超时、网络错误、429 或 5xx 时的最大重试次数
+const UPLOAD_RETRIES: u32 = 2;
+const CHUNK_SIZE: usize = 5 * 1024 * 1024; // 5MB chunks
+const MAX_CONCURRENT_TASKS: usize = 16; // 最大并发任务数
+
+// 上传过程中的错误，transient 表示可以重试
+#[derive(Debug)]
+struct UploadError {
+    message: String,
+    transient: bool,
+}
+
+impl UploadError {
+    fn sdk<E: std::error::Error + 'static>(e: SdkError<E, HttpResponse>) -> Self {
+        let transient = match &e {
+            SdkError::TimeoutError(_) | SdkError::ResponseError(_) => true,
+            SdkError::DispatchFailure(failure) => !failure.is_user(),
+            SdkError::ServiceError(_) => e
+                .raw_response()
+                .map(|response| response.status().as_u16())
+                .is_some_and(|status| status == 429 || (500..600).contains(&status)),
+            _ => false,
+        };
+        Self {
+            message: e.to_string(),
+            transient,
+        }
+    }
+}
+
+// 读取文件、加密等其他错误不重试
+impl From<String> for UploadError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            transient: false,
+        }
+    }
+}
+
+impl From<UploadError> for String {
+    fn from(e: UploadError) -> Self {
+        e.message
+    }
+}
 
 // 键是 file_id，值是一个元组，包含一个 JoinHandle 和一个 Option<String>，用于存储 upload_id，upload_id 用于分段上传
 static UPLOAD_TASKS: Lazy<
@@ -27,89 +89,368 @@ static UPLOAD_TASKS: Lazy<
 
 static UPLOAD_TASKS_INFO: Lazy<DashMap<String, (Arc<R2Client>, String)>> = Lazy::new(DashMap::new);
 
+/// 测试存储桶连接。传入 profile_id 测试已保存的配置，传入 profile 测试尚未保存的配置；
+/// 编辑已有配置时 secret_key 为空则使用已保存的值
 #[tauri::command]
 pub async fn r2_ping(
-    bucket_name: &str,
-    account_id: &str,
-    access_key: &str,
-    secret_key: &str,
+    profile_id: Option<String>,
+    profile: Option<BucketProfile>,
 ) -> Result<(), String> {
-    let client = R2Client::new(bucket_name, account_id, access_key, secret_key, None).await?;
-    client.ping().await
+    let profile = match (profile_id, profile) {
+        (_, Some(mut profile)) => {
+            if profile.secret_key.is_empty() && !profile.id.is_empty() {
+                profile.secret_key = profile::get_profile(&profile.id).await?.secret_key;
+            }
+            profile
+        }
+        (Some(profile_id), None) => profile::get_profile(&profile_id).await?,
+        (None, None) => return Err("缺少存储桶配置".to_string()),
+    };
+    R2Client::from_profile(&profile).await?.ping().await
 }
 
+/// 上传一批文件，返回 batch_id。全部结束后发送 upload-batch 事件，报告可以通过 batch_export 导出
 #[tauri::command]
 pub async fn r2_upload(
     app: AppHandle,
-    bucket_name: &str,
-    account_id: &str,
-    access_key: &str,
-    secret_key: &str,
-    domain: Option<&str>,
+    profile_id: &str,
     files: Vec<File>,
-) -> Result<(), String> {
-    let client =
-        Arc::new(R2Client::new(bucket_name, account_id, access_key, secret_key, domain).await?);
+) -> Result<String, String> {
+    let profile = Arc::new(profile::get_profile(profile_id).await?);
+    let client = Arc::new(R2Client::from_profile(&profile).await?);
+    let batch_id = Arc::new(batch::start(&app, &profile, &files).await);
 
     for file in files {
-        let client = client.clone();
-        let app = app.clone();
-        let filename = file.remote_filename.clone();
-        let file_id = file.id.clone();
-
-        let handle = tokio::spawn(async move {
-            let result = match &file.source {
-                UploadSource::FilePath(path) => {
-                    client
-                        .stream_upload_file(&app, &path, &filename, &file_id.clone())
-                        .await
+        spawn_upload(
+            &app,
+            client.clone(),
+            profile.clone(),
+            file,
+            Some(batch_id.clone()),
+        );
+    }
+
+    Ok(batch_id.to_string())
+}
+
+/// 在后台上传单个文件并登记为上传任务，可以通过 r2_cancel_upload 取消。
+/// 上传结束后通过返回的 Receiver 发送结果，取消时 Receiver 收到 RecvError
+pub fn spawn_upload(
+    app: &AppHandle,
+    client: Arc<R2Client>,
+    profile: Arc<BucketProfile>,
+    file: File,
+    batch_id: Option<Arc<String>>,
+) -> oneshot::Receiver<Result<UploadDetails, String>> {
+    let (sender, receiver) = oneshot::channel();
+    let app = app.clone();
+    let task_id = file.id.clone();
+
+    let handle = tokio::spawn(async move {
+        let batch_id = batch_id.as_deref().map(String::as_str);
+        let result = upload_file(&app, &client, &profile, file, batch_id).await;
+        let uploaded = result.as_ref().map(|_| ()).map_err(Clone::clone);
+        let _ = sender.send(result);
+        uploaded
+    });
+
+    UPLOAD_TASKS.insert(task_id, (handle, None));
+    receiver
+}
+
+// 遇到可重试的错误时按指数退避重试，重试次数累加到 retries。
+// 重试前发送 upload-retry 事件，之后的进度会从 0 重新开始
+async fn with_retries<F, Fut>(
+    app: &AppHandle,
+    file_id: &str,
+    retries: &mut u32,
+    mut upload: F,
+) -> Result<String, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<String, UploadError>>,
+{
+    loop {
+        match upload().await {
+            Err(e) if e.transient && *retries < UPLOAD_RETRIES => {
+                let delay = Duration::from_secs(1 << *retries);
+                *retries += 1;
+                let _ = app.emit(
+                    "upload-retry",
+                    UploadRetry {
+                        file_id: file_id.to_string(),
+                        attempt: *retries,
+                        max_retries: UPLOAD_RETRIES,
+                        delay_secs: delay.as_secs(),
+                        error: e.message,
+                    },
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result.map_err(String::from),
+        }
+    }
+}
+
+// 上传单个文件：上传前处理、上传原文件与变体、记录历史并发送进度事件。
+// batch_id 为空时不记录到批次报告
+async fn upload_file(
+    app: &AppHandle,
+    client: &R2Client,
+    profile: &BucketProfile,
+    file: File,
+    batch_id: Option<&str>,
+) -> Result<UploadDetails, String> {
+    let mut filename = file.remote_filename.clone();
+    let file_id = file.id.clone();
+    let started = Instant::now();
+    let local_path = file.source.local_path().map(str::to_string);
+    // 批次开始时已写入历史记录；历史记录写入失败不影响上传
+    let history_id = match batch_id.and_then(|batch_id| batch::history_id(batch_id, &file_id)) {
+        Some(id) => Some(id),
+        None => history::record_start(
+            app,
+            UploadStarted {
+                file_id: file_id.clone(),
+                profile_id: profile.id.clone(),
+                bucket: profile.bucket_name.clone(),
+                key: filename.clone(),
+                local_path: local_path.clone(),
+                batch_id: batch_id.map(str::to_string),
+            },
+        )
+        .await
+        .ok(),
+    };
+
+    let mut details = UploadDetails::default();
+    let mut size = None;
+    let mut content_type = None;
+    // 回调只发送用户设置的元数据，不包含加密参数等内部元数据；
+    // 键与值与上传的对象元数据一致，元数据无效时上传失败，回调中为空
+    let metadata =
+        upload_options::normalize_metadata(&file.headers.merged(&profile.default_headers).metadata)
+            .unwrap_or_default();
+    let result = match pipeline::prepare_upload(&file, profile).await {
+        Err(e) => Err(e),
+        Ok(prepared) => {
+            filename = prepared.key;
+            details = prepared.details;
+            size = Some(prepared.size);
+            content_type = Some(prepared.content_type.clone());
+            // 每次尝试都使用新的加密器，重试时重新读取的文件内容可能已经变化
+            let options = &prepared.options;
+            let (filename, file_id) = (&filename, &file_id);
+            let uploaded = match prepared.body {
+                UploadBody::File(path) => {
+                    let path = &path;
+                    with_retries(app, file_id, &mut details.retries, || async move {
+                        let options = options.renew_cipher();
+                        client
+                            .stream_upload_file(app, path, filename, file_id, &options)
+                            .await
+                    })
+                    .await
                 }
-                UploadSource::FileContent(content) => {
-                    emit_progress(
-                        &app,
-                        format!("{}/{}", client.domain, filename),
-                        file_id.clone(),
-                        filename.clone(),
-                        UploadStatus::Uploading {
-                            progress: 0.0,
-                            bytes_uploaded: 0,
-                            total_bytes: content.len() as u64,
-                            speed: 0.0,
-                        },
-                    );
-                    client.upload_content(content, &filename).await
+                UploadBody::Bytes(data) => {
+                    let data = &data;
+                    with_retries(app, file_id, &mut details.retries, || async move {
+                        let options = options.renew_cipher();
+                        client
+                            .upload_bytes(app, data.clone(), filename, file_id, &options)
+                            .await
+                    })
+                    .await
                 }
+                // 数据流无法重放，不重试
+                UploadBody::Stream { reader, .. } => client
+                    .stream_upload(
+                        app,
+                        reader,
+                        prepared.size,
+                        filename,
+                        file_id,
+                        &prepared.options,
+                    )
+                    .await
+                    .map_err(String::from),
             };
+            match uploaded {
+                Ok(sha256) => {
+                    details.sha256 = Some(sha256);
+                    details.links = profile.links.format_all(&LinkContext {
+                        url: &client.public_url(filename),
+                        key: filename,
+                        content_type: &prepared.content_type,
+                        size: prepared.size,
+                        dimensions: prepared.dimensions,
+                        encrypted: prepared.options.cipher.is_some(),
+                    });
+                    upload_variants(client, prepared.variants, &mut details).await
+                }
+                Err(e) => Err(e),
+            }
+        }
+    };
 
-            emit_progress(
-                &app,
-                format!("{}/{}", client.domain, filename),
-                file_id,
-                filename,
-                match &result {
-                    Ok(_) => UploadStatus::Success,
-                    Err(e) => UploadStatus::Error {
-                        message: e.to_string(),
-                        code: "UPLOAD_ERROR".to_string(),
-                    },
-                },
-            );
+    let status = match &result {
+        Ok(_) => HistoryStatus::Success,
+        Err(_) => HistoryStatus::Error,
+    };
+    let duration_ms = started.elapsed().as_millis() as u64;
 
-            result
+    // 回调在后台执行，不延迟上传完成事件
+    if !profile.hooks.is_empty() {
+        let settings = profile.hooks.clone();
+        let payload = HookPayload {
+            event: match &result {
+                Ok(_) => HookEvent::Success,
+                Err(_) => HookEvent::Failure,
+            },
+            file_id: file_id.clone(),
+            bucket: profile.bucket_name.clone(),
+            key: filename.clone(),
+            url: client.public_url(&filename),
+            local_path,
+            size,
+            content_type: content_type.clone(),
+            sha256: details.sha256.clone(),
+            metadata,
+            error: result.as_ref().err().cloned(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+        let app = app.clone();
+        tokio::spawn(async move {
+            let errors = hooks::run(&settings, &payload).await;
+            if !errors.is_empty() {
+                let _ = app.emit(
+                    "upload-hook-error",
+                    HookError {
+                        file_id: payload.file_id,
+                        key: payload.key,
+                        errors,
+                    },
+                );
+            }
         });
+    }
 
-        UPLOAD_TASKS.insert(file.id.clone(), (handle, None));
+    if let Some(id) = history_id {
+        let _ = history::record_finish(
+            app,
+            id,
+            UploadFinished {
+                key: filename.clone(),
+                url: client.public_url(&filename),
+                size,
+                content_type,
+                sha256: details.sha256.clone(),
+                duration_ms,
+                status,
+                error: result.as_ref().err().cloned(),
+                details: Some(details.clone()),
+            },
+        )
+        .await;
+    }
+    // 批次报告从上传历史中读取，需要在写入历史之后
+    if let Some(batch_id) = batch_id {
+        batch::record_finish(app, batch_id, &file_id).await;
     }
 
+    emit_result(
+        app,
+        client.public_url(&filename),
+        file_id,
+        filename,
+        match &result {
+            Ok(_) => UploadStatus::Success,
+            Err(e) => UploadStatus::Error {
+                message: e.to_string(),
+                code: "UPLOAD_ERROR".to_string(),
+            },
+        },
+        Some(details.clone()),
+    );
+
+    result.map(|_| details)
+}
+
+// 原文件上传成功后依次上传图片变体，并记录变体的链接
+async fn upload_variants(
+    client: &R2Client,
+    variants: Vec<PreparedVariant>,
+    details: &mut UploadDetails,
+) -> Result<(), String> {
+    for variant in variants {
+        let size = variant.data.len() as u64;
+        client
+            .put_bytes(&variant.key, variant.data, &variant.options)
+            .await?;
+        details.variants.push(VariantLink {
+            url: client.public_url(&variant.key),
+            key: variant.key,
+            width: variant.width,
+            height: variant.height,
+            size,
+        });
+    }
     Ok(())
 }
 
+#[tauri::command]
+pub async fn r2_head_object(profile_id: &str, key: &str) -> Result<ObjectInfo, String> {
+    let profile = profile::get_profile(profile_id).await?;
+    R2Client::from_profile(&profile)
+        .await?
+        .head_object(key)
+        .await
+}
+
+#[tauri::command]
+pub async fn r2_copy_object(
+    profile_id: &str,
+    source_key: &str,
+    dest_key: &str,
+) -> Result<(), String> {
+    let profile = profile::get_profile(profile_id).await?;
+    R2Client::from_profile(&profile)
+        .await?
+        .copy_object(source_key, dest_key)
+        .await
+}
+
+/// 下载对象到本地文件，对象经过客户端加密时自动解密
+#[tauri::command]
+pub async fn r2_download(profile_id: &str, key: &str, dest_path: &str) -> Result<(), String> {
+    let profile = profile::get_profile(profile_id).await?;
+    let client = R2Client::from_profile(&profile).await?;
+    client
+        .download_to_file(key, dest_path, &profile.encryption)
+        .await
+}
+
 pub fn emit_progress(
     app: &AppHandle,
     url: String,
     file_id: String,
     filename: String,
     status: UploadStatus,
+) {
+    emit_result(app, url, file_id, filename, status, None);
+}
+
+// 与 emit_progress 相同，但附带上传前处理的详细信息
+pub fn emit_result(
+    app: &AppHandle,
+    url: String,
+    file_id: String,
+    filename: String,
+    status: UploadStatus,
+    details: Option<UploadDetails>,
 ) {
     let _ = app.emit(
         "upload-progress",
@@ -122,6 +463,7 @@ pub fn emit_progress(
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
+            details,
         },
     );
 }
@@ -153,6 +495,8 @@ pub async fn r2_cancel_upload(app: AppHandle, file_id: String) -> Result<(), Str
         // Finally remove the entries
         UPLOAD_TASKS.remove(&file_id);
         UPLOAD_TASKS_INFO.remove(&file_id);
+        let _ = history::record_cancelled(&app, &file_id).await;
+        batch::record_cancelled(&app, &file_id).await;
 
         // emit
         emit_progress(
@@ -172,12 +516,33 @@ pub struct R2Client {
     client: Client,
     bucket_name: String,
     domain: String,
+    sse_customer_key: Option<SseCustomerKey>,
 }
 
 impl R2Client {
-    pub async fn new(
+    pub async fn from_profile(profile: &BucketProfile) -> Result<Self, String> {
+        let mut client = Self::with_endpoint(
+            &profile.bucket_name,
+            &profile.endpoint_url(),
+            &profile.region_name(),
+            &profile.access_key,
+            &profile.secret_key,
+            profile.domain.as_deref(),
+        )
+        .await?;
+        client.sse_customer_key = profile
+            .sse_customer_key
+            .as_deref()
+            .filter(|key| !key.is_empty())
+            .map(SseCustomerKey::from_base64)
+            .transpose()?;
+        Ok(client)
+    }
+
+    async fn with_endpoint(
         bucket_name: &str,
-        account_id: &str,
+        endpoint_url: &str,
+        region: &str,
         access_key: &str,
         secret_key: &str,
         domain: Option<&str>,
@@ -195,8 +560,8 @@ impl R2Client {
             .build();
 
         let mut config_loader = ConfigLoader::default()
-            .region(Region::new("auto"))
-            .endpoint_url(format!("https://{}.r2.cloudflarestorage.com", account_id))
+            .region(Region::new(region.to_string()))
+            .endpoint_url(endpoint_url)
             .timeout_config(timeout_config)
             .credentials_provider(credentials);
 
@@ -211,40 +576,119 @@ impl R2Client {
             client: Client::new(&config),
             bucket_name: bucket_name.to_string(),
             domain: domain.unwrap_or("").to_string(),
+            sse_customer_key: None,
         })
     }
 
-    // 上传文件内容，一般是文字或图片，内容不会太大，直接上传，且不需要进度
-    pub async fn upload_content(&self, content: &str, remote_filename: &str) -> Result<(), String> {
+    // 上传内存中的数据，一般是文字、粘贴的图片或经过优化的图片，内容不会太大，直接上传。
+    // 返回内容的 SHA-256
+    async fn upload_bytes(
+        &self,
+        app: &AppHandle,
+        data: Vec<u8>,
+        remote_filename: &str,
+        file_id: &str,
+        options: &UploadOptions,
+    ) -> Result<String, UploadError> {
+        emit_progress(
+            app,
+            self.public_url(remote_filename),
+            file_id.to_string(),
+            remote_filename.to_string(),
+            UploadStatus::Uploading {
+                progress: 0.0,
+                bytes_uploaded: 0,
+                total_bytes: data.len() as u64,
+                speed: 0.0,
+            },
+        );
+        let sha256 = format!("{:x}", Sha256::digest(&data));
+        self.put_bytes(remote_filename, data, options).await?;
+        Ok(sha256)
+    }
+
+    async fn put_raw(
+        &self,
+        remote_filename: &str,
+        data: Vec<u8>,
+        options: &UploadOptions,
+    ) -> Result<(), UploadError> {
         self.client
             .put_object()
             .bucket(&self.bucket_name)
             .key(remote_filename)
-            .body(content.as_bytes().to_vec().into())
-            .content_type(
-                from_path(remote_filename)
-                    .first_or_octet_stream()
-                    .to_string(),
-            )
+            .sse_customer_key_opt(self.sse_customer_key.as_ref())
+            .body(data.into())
+            .upload_options(options)
             .send()
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(UploadError::sdk)?;
         Ok(())
     }
 
+    // 上传内存中的数据，按压缩规则先进行压缩
+    async fn put_bytes(
+        &self,
+        remote_filename: &str,
+        data: Vec<u8>,
+        options: &UploadOptions,
+    ) -> Result<(), UploadError> {
+        if let Some(cipher) = &options.cipher {
+            let data = cipher.encrypt(&data, 0, true)?;
+            return self.put_raw(remote_filename, data, options).await;
+        }
+
+        let Some(plan) = options.compression else {
+            return self.put_raw(remote_filename, data, options).await;
+        };
+
+        let (data, compressed) = tokio::task::spawn_blocking(move || {
+            let compressed = compression::compress(&data, plan.encoding, plan.level);
+            (data, compressed)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        let compressed = compressed?;
+
+        // 压缩后没有变小，直接上传原始数据
+        if compressed.len() >= data.len() {
+            return self.put_raw(remote_filename, data, options).await;
+        }
+
+        let compressed_options = UploadOptions {
+            content_encoding: Some(plan.encoding.header_value().to_string()),
+            compression: None,
+            ..options.clone()
+        };
+        if plan.upload_variants {
+            self.put_raw(remote_filename, data, options).await?;
+            let variant = format!("{}{}", remote_filename, plan.encoding.extension());
+            self.put_raw(&variant, compressed, &compressed_options)
+                .await
+        } else {
+            self.put_raw(remote_filename, compressed, &compressed_options)
+                .await
+        }
+    }
+
     // 创建多部分上传
-    async fn create_multipart_upload(&self, remote_filename: &str) -> Result<String, String> {
+    async fn create_multipart_upload(
+        &self,
+        remote_filename: &str,
+        options: &UploadOptions,
+    ) -> Result<String, UploadError> {
         self.client
             .create_multipart_upload()
             .bucket(&self.bucket_name)
             .key(remote_filename)
-            .content_type(from_path(remote_filename).first_or_octet_stream().as_ref())
+            .sse_customer_key_opt(self.sse_customer_key.as_ref())
+            .upload_options(options)
             .send()
             .await
-            .map_err(|e| e.to_string())?
+            .map_err(UploadError::sdk)?
             .upload_id()
-            .ok_or_else(|| "Failed to get upload ID".to_string())
             .map(|id| id.to_string())
+            .ok_or_else(|| "Failed to get upload ID".to_string().into())
     }
 
     async fn complete_multipart_upload(
@@ -252,11 +696,12 @@ impl R2Client {
         remote_filename: &str,
         upload_id: &str,
         parts: Vec<CompletedPart>,
-    ) -> Result<(), String> {
+    ) -> Result<(), UploadError> {
         self.client
             .complete_multipart_upload()
             .bucket(&self.bucket_name)
             .key(remote_filename)
+            .sse_customer_key_opt(self.sse_customer_key.as_ref())
             .upload_id(upload_id)
             .multipart_upload(
                 CompletedMultipartUpload::builder()
@@ -267,7 +712,7 @@ impl R2Client {
             .await
             .map_err(|e| {
                 println!("完成多部分上传时遇到错误：{}", e.to_string());
-                e.to_string()
+                UploadError::sdk(e)
             })?;
         Ok(())
     }
@@ -278,19 +723,20 @@ impl R2Client {
         upload_id: &str,
         part_number: i32,
         body: Vec<u8>,
-    ) -> Result<CompletedPart, String> {
+    ) -> Result<CompletedPart, UploadError> {
         self.client
             .upload_part()
             .bucket(&self.bucket_name)
             .key(remote_filename)
+            .sse_customer_key_opt(self.sse_customer_key.as_ref())
             .upload_id(upload_id)
             .part_number(part_number)
             .body(aws_sdk_s3::primitives::ByteStream::from(body))
             .send()
             .await
-            .map_err(|e| e.to_string())?
+            .map_err(UploadError::sdk)?
             .e_tag()
-            .ok_or_else(|| "Failed to get ETag".to_string())
+            .ok_or_else(|| "Failed to get ETag".to_string().into())
             .map(|e_tag| {
                 CompletedPart::builder()
                     .e_tag(e_tag)
@@ -305,20 +751,33 @@ impl R2Client {
         path: &str,
         remote_filename: &str,
         file_id: &str,
-    ) -> Result<(), String> {
-        const CHUNK_SIZE: usize = 5 * 1024 * 1024; // 5MB chunks
-        const MAX_CONCURRENT_TASKS: usize = 16; // 最大并发任务数
-
+        options: &UploadOptions,
+    ) -> Result<String, UploadError> {
         // 读取文件信息
-        let mut file = tokio::fs::File::open(path)
+        let file = tokio::fs::File::open(path)
             .await
             .map_err(|e| e.to_string())?;
-        let file_size = file.metadata().await.map_err(|e| e.to_string())?.len() as usize;
+        let file_size = file.metadata().await.map_err(|e| e.to_string())?.len();
+        self.stream_upload(app, file, file_size, remote_filename, file_id, options)
+            .await
+    }
+
+    // 上传长度已知的数据流，如本地文件或远程 URL 的响应体，返回内容的 SHA-256
+    async fn stream_upload(
+        &self,
+        app: &tauri::AppHandle,
+        mut file: impl AsyncRead + Unpin,
+        file_size: u64,
+        remote_filename: &str,
+        file_id: &str,
+        options: &UploadOptions,
+    ) -> Result<String, UploadError> {
+        let file_size = file_size as usize;
 
         // 首次报告
         emit_progress(
             &app,
-            format!("{}/{}", self.domain, remote_filename),
+            self.public_url(remote_filename),
             file_id.to_string(),
             remote_filename.to_string(),
             UploadStatus::Uploading {
@@ -329,30 +788,25 @@ impl R2Client {
             },
         );
 
-        // 如果文件小于 CHUNK_SIZE，直接上传
-        if file_size < CHUNK_SIZE {
+        // 如果文件小于 CHUNK_SIZE 或需要压缩，直接上传
+        if file_size < CHUNK_SIZE || options.compression.is_some() {
             let mut buffer = Vec::new();
             file.read_to_end(&mut buffer)
                 .await
                 .map_err(|e| e.to_string())?;
-            self.client
-                .put_object()
-                .bucket(&self.bucket_name)
-                .key(remote_filename)
-                .body(buffer.into())
-                .content_type(
-                    from_path(remote_filename)
-                        .first_or_octet_stream()
-                        .to_string(),
-                )
-                .send()
-                .await
-                .map_err(|e| e.to_string())?;
-            return Ok(());
+            // 文件在上传过程中被修改，或远程响应体提前结束
+            if buffer.len() != file_size {
+                return Err("读取的数据长度与文件大小不一致".to_string().into());
+            }
+            let sha256 = format!("{:x}", Sha256::digest(&buffer));
+            self.put_bytes(remote_filename, buffer, options).await?;
+            return Ok(sha256);
         }
 
         // 大文件，分块上传
-        let upload_id = self.create_multipart_upload(remote_filename).await?;
+        let upload_id = self
+            .create_multipart_upload(remote_filename, options)
+            .await?;
 
         // Store upload_id in UPLOAD_TASKS
         if let Some(mut entry) = UPLOAD_TASKS.get_mut(file_id) {
@@ -365,90 +819,121 @@ impl R2Client {
             (Arc::new(self.clone()), remote_filename.to_string()),
         );
 
-        let start_time = SystemTime::now();
-        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_TASKS)); // 限制并发任务数
+        // 读取数据并并行上传各分块。失败时停止剩余的分块并中止分块上传，
+        // 重试会创建新的上传，不能留下未完成的上传
         let mut tasks = Vec::new();
-        let mut part_number = 1;
-        let bytes_uploaded = Arc::new(AtomicUsize::new(0)); // 用于跟踪实际上传的字节数
-        let mut file_offset = 0; // 用于跟踪文件的读取偏移量
-
-        // 读取文件并分块上传
-        loop {
-            // 获取 Semaphore 许可
-            let semaphore = semaphore.clone();
-            let permit = semaphore.acquire_owned().await.map_err(|e| e.to_string())?;
-
-            let remaining_bytes = file_size - file_offset; // 基于文件偏移量计算剩余字节
-            let buffer_size = if remaining_bytes > CHUNK_SIZE {
-                CHUNK_SIZE
-            } else {
-                remaining_bytes
-            };
+        let result = async {
+            let start_time = SystemTime::now();
+            let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_TASKS)); // 限制并发任务数
+            let mut part_number = 1;
+            let bytes_uploaded = Arc::new(AtomicUsize::new(0)); // 用于跟踪实际上传的字节数
+            let mut file_offset = 0; // 用于跟踪文件的读取偏移量
+            let mut hasher = Sha256::new(); // 按读取顺序计算整个文件的哈希
 
-            if buffer_size == 0 {
-                break; // 文件已读取完毕
-            }
+            // 读取文件并分块上传
+            loop {
+                // 获取 Semaphore 许可
+                let semaphore = semaphore.clone();
+                let permit = semaphore.acquire_owned().await.map_err(|e| e.to_string())?;
 
-            let mut buffer = vec![0; buffer_size];
-            file.read_exact(&mut buffer)
-                .await
-                .map_err(|e| e.to_string())?;
+                let remaining_bytes = file_size - file_offset; // 基于文件偏移量计算剩余字节
+                let buffer_size = if remaining_bytes > CHUNK_SIZE {
+                    CHUNK_SIZE
+                } else {
+                    remaining_bytes
+                };
 
-            // 克隆需要的变量以在任务中使用
-            let client = self.clone();
-            let remote_filename = remote_filename.to_string();
-            let upload_id = upload_id.clone();
-            let app = app.clone();
-            let file_id = file_id.to_string();
-            let domain = self.domain.clone();
-            let bytes_uploaded = bytes_uploaded.clone();
-
-            // 启动并行上传任务
-            let task = tokio::spawn(async move {
-                let part = client
-                    .upload_part(&remote_filename, &upload_id, part_number, buffer.to_vec())
-                    .await?;
-
-                // 更新实际上传的字节数
-                bytes_uploaded.fetch_add(buffer_size, Ordering::SeqCst);
-
-                // 更新进度
-                let elapsed = SystemTime::now()
-                    .duration_since(start_time)
-                    .unwrap_or_default();
-                let uploaded = bytes_uploaded.load(Ordering::SeqCst);
-                let speed = uploaded as f64 / elapsed.as_secs_f64();
-                emit_progress(
-                    &app,
-                    format!("{}/{}", domain, remote_filename),
-                    file_id,
-                    remote_filename,
-                    UploadStatus::Uploading {
-                        progress: uploaded as f64 / file_size as f64,
-                        bytes_uploaded: (part_number as usize * CHUNK_SIZE) as u64,
-                        total_bytes: file_size as u64,
-                        speed,
-                    },
-                );
+                if buffer_size == 0 {
+                    break; // 文件已读取完毕
+                }
 
-                // 释放 Semaphore 许可
-                drop(permit);
+                let mut buffer = vec![0; buffer_size];
+                file.read_exact(&mut buffer)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                hasher.update(&buffer);
 
-                Ok::<_, String>(part)
-            });
+                // 克隆需要的变量以在任务中使用
+                let client = self.clone();
+                let remote_filename = remote_filename.to_string();
+                let upload_id = upload_id.to_string();
+                let app = app.clone();
+                let file_id = file_id.to_string();
+                let bytes_uploaded = bytes_uploaded.clone();
+                let cipher = options.cipher.clone();
+                let is_last_part = file_offset + buffer_size == file_size;
 
-            tasks.push(task);
-            file_offset += buffer_size; // 更新文件读取偏移量
-            part_number += 1;
-        }
+                // 启动并行上传任务
+                let task = tokio::spawn(async move {
+                    // 客户端加密：每个分块的明文长度是加密分段的整数倍，分段序号可由分块序号推出
+                    let body = match cipher {
+                        Some(cipher) => cipher.encrypt(
+                            &buffer,
+                            ((part_number as usize - 1) * (CHUNK_SIZE / encryption::SEGMENT_LEN))
+                                as u32,
+                            is_last_part,
+                        )?,
+                        None => buffer,
+                    };
+                    let part = client
+                        .upload_part(&remote_filename, &upload_id, part_number, body)
+                        .await?;
 
-        // 等待所有任务完成
-        let results = futures::future::try_join_all(tasks).await.unwrap();
-        let completed_parts: Vec<_> = results.into_iter().collect::<Result<_, _>>()?;
+                    // 更新实际上传的字节数
+                    bytes_uploaded.fetch_add(buffer_size, Ordering::SeqCst);
 
-        // 完成分块上传
-        self.complete_multipart_upload(remote_filename, &upload_id, completed_parts)
-            .await
+                    // 更新进度
+                    let elapsed = SystemTime::now()
+                        .duration_since(start_time)
+                        .unwrap_or_default();
+                    let uploaded = bytes_uploaded.load(Ordering::SeqCst);
+                    let speed = uploaded as f64 / elapsed.as_secs_f64();
+                    emit_progress(
+                        &app,
+                        client.public_url(&remote_filename),
+                        file_id,
+                        remote_filename,
+                        UploadStatus::Uploading {
+                            progress: uploaded as f64 / file_size as f64,
+                            bytes_uploaded: (part_number as usize * CHUNK_SIZE) as u64,
+                            total_bytes: file_size as u64,
+                            speed,
+                        },
+                    );
+
+                    // 释放 Semaphore 许可
+                    drop(permit);
+
+                    Ok::<_, UploadError>(part)
+                });
+
+                tasks.push(task);
+                file_offset += buffer_size; // 更新文件读取偏移量
+                part_number += 1;
+            }
+
+            // 等待所有任务完成，任一分块失败时立即返回
+            let completed_parts =
+                futures::future::try_join_all(tasks.iter_mut().map(|task| async {
+                    task.await.map_err(|e| UploadError::from(e.to_string()))?
+                }))
+                .await?;
+
+            // 完成分块上传
+            self.complete_multipart_upload(remote_filename, &upload_id, completed_parts)
+                .await?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+        .await;
+        if result.is_err() {
+            for task in &tasks {
+                task.abort();
+            }
+            let _ = self
+                .abort_multipart_upload(remote_filename, &upload_id)
+                .await;
+        }
+        result
     }
 
     async fn abort_multipart_upload(
@@ -467,6 +952,101 @@ impl R2Client {
         Ok(())
     }
 
+    async fn download_to_file(
+        &self,
+        key: &str,
+        dest_path: &str,
+        encryption: &EncryptionConfig,
+    ) -> Result<(), String> {
+        let mut response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .sse_customer_key_opt(self.sse_customer_key.as_ref())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let metadata = response.metadata().cloned().unwrap_or_default();
+        let mut decryptor = ObjectDecryptor::from_metadata(encryption, &metadata).await?;
+
+        // 先写入临时文件，完成后再改名，避免解密失败时留下不完整的文件
+        let tmp_path = format!("{}.part", dest_path);
+        let result = async {
+            let mut file = tokio::fs::File::create(&tmp_path)
+                .await
+                .map_err(|e| e.to_string())?;
+            while let Some(chunk) = response.body.try_next().await.map_err(|e| e.to_string())? {
+                let data = match &mut decryptor {
+                    Some(decryptor) => decryptor.update(&chunk)?,
+                    None => chunk.to_vec(),
+                };
+                file.write_all(&data).await.map_err(|e| e.to_string())?;
+            }
+            if let Some(decryptor) = decryptor {
+                file.write_all(&decryptor.finish()?)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            file.flush().await.map_err(|e| e.to_string())
+        }
+        .await;
+
+        match result {
+            Ok(()) => tokio::fs::rename(&tmp_path, dest_path)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn head_object(&self, key: &str) -> Result<ObjectInfo, String> {
+        let response = self
+            .client
+            .head_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .sse_customer_key_opt(self.sse_customer_key.as_ref())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(ObjectInfo {
+            key: key.to_string(),
+            url: self.public_url(key),
+            size: response.content_length().unwrap_or_default().max(0) as u64,
+            content_type: response.content_type().map(|t| t.to_string()),
+            e_tag: response.e_tag().map(|t| t.to_string()),
+            last_modified: response.last_modified().map(|t| t.secs()),
+            metadata: response.metadata().cloned().unwrap_or_default(),
+        })
+    }
+
+    // 同一存储桶内复制对象，源对象与目标对象使用相同的 SSE-C 密钥
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<(), String> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket_name)
+            .key(dest_key)
+            .copy_source(format!(
+                "{}/{}",
+                self.bucket_name,
+                key_rules::encode_key(source_key)
+            ))
+            .sse_customer_key_opt(self.sse_customer_key.as_ref())
+            .copy_source_sse_customer_key_opt(self.sse_customer_key.as_ref())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn public_url(&self, key: &str) -> String {
+        key_rules::public_url(&self.domain, key)
+    }
+
     pub async fn ping(&self) -> Result<(), String> {
         println!("ping...");
         self.client
@@ -479,20 +1059,23 @@ impl R2Client {
     }
 }
 
-fn create_proxy_connector() -> Option<ProxyConnector<HttpConnector>> {
+/// 已启用的系统代理地址，如 `http://127.0.0.1:7890`
+pub fn system_proxy_uri() -> Option<String> {
     #[cfg(any(target_os = "ios", target_os = "android"))]
     return None;
 
     #[cfg(not(any(target_os = "ios", target_os = "android")))]
     match sysproxy::Sysproxy::get_system_proxy() {
         Ok(proxy) if !proxy.host.is_empty() && proxy.port > 0 && proxy.enable => {
-            // Try to create proxy URI and connector
-            let proxy_uri = format!("http://{}:{}", proxy.host, proxy.port)
-                .parse()
-                .ok()?;
-            let proxy = hyper_proxy::Proxy::new(hyper_proxy::Intercept::All, proxy_uri);
-            ProxyConnector::from_proxy(HttpConnector::new(), proxy).ok()
+            Some(format!("http://{}:{}", proxy.host, proxy.port))
         }
         _ => None, // Return None if no proxy or error getting proxy
     }
 }
+
+fn create_proxy_connector() -> Option<ProxyConnector<HttpConnector>> {
+    // Try to create proxy URI and connector
+    let proxy_uri = system_proxy_uri()?.parse().ok()?;
+    let proxy = hyper_proxy::Proxy::new(hyper_proxy::Intercept::All, proxy_uri);
+    ProxyConnector::from_proxy(HttpConnector::new(), proxy).ok()
+}
@@ -0,0 +1,182 @@
+use crate::mime_detect;
+use futures::StreamExt;
+use percent_encoding::percent_decode_str;
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use std::io::Cursor;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::StreamReader;
+
+// 未配置时远程文件的大小上限
+pub const DEFAULT_MAX_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+// 服务器超过该时间没有发送数据时中断下载
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+// 无法从响应头与 URL 推断文件名时使用
+const FALLBACK_FILENAME: &str = "download";
+
+/// 远程 URL 的响应体，以流的形式读取，不写入磁盘
+pub struct RemoteSource {
+    /// 包含 header 在内的完整响应体
+    pub reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+    /// 响应体开头用于嗅探类型的字节
+    pub header: Vec<u8>,
+    /// Content-Length，服务器未提供时为空
+    pub size: Option<u64>,
+    /// 从 Content-Disposition 或 URL 推断的文件名
+    pub filename: String,
+    /// 响应的 Content-Type，不含参数
+    pub content_type: Option<String>,
+}
+
+impl RemoteSource {
+    /// 读取完整的响应体，用于未提供 Content-Length 或需要处理内容的情况
+    pub async fn read_to_end(self, max_size: u64) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        self.reader
+            .take(max_size + 1)
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| format!("下载远程文件失败：{}", e))?;
+        if data.len() as u64 > max_size {
+            return Err(too_large(max_size));
+        }
+        Ok(data)
+    }
+}
+
+fn too_large(max_size: u64) -> String {
+    format!("远程文件超过 {}MB 限制", max_size / 1024 / 1024)
+}
+
+/// 请求远程 URL，检查状态码与大小，并读取开头的字节用于嗅探类型
+pub async fn open(url: &str, max_size: u64) -> Result<RemoteSource, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("URL 格式错误：{}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("只支持 http 与 https URL：{}", url));
+    }
+
+    let mut builder = reqwest::Client::builder().connect_timeout(CONNECT_TIMEOUT);
+    // 与上传使用同一个系统代理
+    if let Some(proxy) = crate::r2::system_proxy_uri() {
+        let proxy = reqwest::Proxy::all(proxy).map_err(|e| e.to_string())?;
+        builder = builder.proxy(proxy);
+    }
+    let client = builder.build().map_err(|e| e.to_string())?;
+    let response = client
+        .get(parsed.clone())
+        .send()
+        .await
+        .map_err(|e| format!("无法请求远程文件：{}", e))?
+        .error_for_status()
+        .map_err(|e| format!("远程服务器返回错误：{}", e))?;
+
+    let size = response.content_length();
+    if size.is_some_and(|size| size > max_size) {
+        return Err(too_large(max_size));
+    }
+
+    let headers = response.headers();
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| value.contains('/'));
+    let filename = headers
+        .get(CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(disposition_filename)
+        .or_else(|| url_filename(&parsed))
+        .map(|name| with_extension(name, content_type.as_deref()))
+        .unwrap_or_else(|| FALLBACK_FILENAME.to_string());
+
+    // 对每个分块的读取设置超时，连接建立后服务器停止响应不会让上传一直挂起
+    let stream = futures::stream::unfold(response.bytes_stream(), |mut stream| async move {
+        let item = match tokio::time::timeout(READ_TIMEOUT, stream.next()).await {
+            Ok(item) => item?.map_err(std::io::Error::other),
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("超过 {} 秒没有收到数据", READ_TIMEOUT.as_secs()),
+            )),
+        };
+        Some((item, stream))
+    });
+    let mut body = StreamReader::new(Box::pin(stream));
+    let mut header = Vec::with_capacity(mime_detect::SNIFF_LEN);
+    (&mut body)
+        .take(mime_detect::SNIFF_LEN as u64)
+        .read_to_end(&mut header)
+        .await
+        .map_err(|e| format!("下载远程文件失败：{}", e))?;
+
+    // 已知大小时只读取声明的长度，响应体提前结束时上传会报错
+    let reader: Box<dyn AsyncRead + Send + Sync + Unpin> = match size {
+        Some(size) => Box::new(Cursor::new(header.clone()).chain(body).take(size)),
+        None => Box::new(Cursor::new(header.clone()).chain(body)),
+    };
+    Ok(RemoteSource {
+        reader,
+        header,
+        size,
+        filename,
+        content_type,
+    })
+}
+
+// 解析 `attachment; filename="a.png"; filename*=UTF-8''%E5%9B%BE.png`，filename* 优先
+fn disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    for param in value.split(';').skip(1) {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match name.trim().to_lowercase().as_str() {
+            "filename*" => {
+                let encoded = value.splitn(3, '\'').nth(2).unwrap_or(value);
+                if let Ok(decoded) = percent_decode_str(encoded).decode_utf8() {
+                    return sanitize(&decoded);
+                }
+            }
+            "filename" => plain = sanitize(value),
+            _ => {}
+        }
+    }
+    plain
+}
+
+fn url_filename(url: &reqwest::Url) -> Option<String> {
+    let segment = url.path_segments()?.next_back()?;
+    sanitize(&percent_decode_str(segment).decode_utf8_lossy())
+}
+
+// 去掉路径部分，避免文件名中的 `/`、`\` 影响 key
+fn sanitize(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
+// 文件名没有扩展名时按 Content-Type 补上
+fn with_extension(name: String, content_type: Option<&str>) -> String {
+    if std::path::Path::new(&name).extension().is_some() {
+        return name;
+    }
+    let Some(content_type) = content_type else {
+        return name;
+    };
+    // mime_guess 的候选按字母排序，常见类型使用惯用的扩展名
+    let extension = match content_type {
+        "image/jpeg" => Some("jpg"),
+        "text/plain" => Some("txt"),
+        "audio/mpeg" => Some("mp3"),
+        "video/mpeg" => Some("mpg"),
+        "text/html" => Some("html"),
+        _ => mime_guess::get_mime_extensions_str(content_type)
+            .and_then(|extensions| extensions.first().copied()),
+    };
+    match extension {
+        Some(extension) => format!("{}.{}", name, extension),
+        None => name,
+    }
+}
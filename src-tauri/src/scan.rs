@@ -0,0 +1,549 @@
+use crate::ignore_rules::{IgnoreChain, IgnoreMatcher, ScanRules};
+use crate::mime_detect;
+use crate::profile;
+use crate::source;
+use crate::typ::FileDetail;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+// 每批最多包含的条目数，以及两批之间的最长间隔
+const BATCH_SIZE: usize = 500;
+const BATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+// 键是 scan_id，值是取消标记
+static SCAN_TASKS: Lazy<DashMap<String, Arc<AtomicBool>>> = Lazy::new(DashMap::new);
+
+/// 扫描选项，规则语法与 .gitignore 相同
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanOptions {
+    // 为空时包含所有文件
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    // 是否读取目录中的 .gitignore 与 .r2ignore
+    pub respect_ignore_files: bool,
+    // 使用该存储桶的默认排除规则，为空时使用内置的默认规则
+    pub profile_id: Option<String>,
+    pub symlinks: SymlinkPolicy,
+    // 是否包含隐藏文件（`.` 开头，或 Windows 上带隐藏属性）
+    pub include_hidden: bool,
+    // 是否读取每个文件的文件头嗅探 MIME 类型。默认只按扩展名推断，
+    // 预览与上传时会再读取文件头
+    pub sniff_mime: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_ignore_files: true,
+            profile_id: None,
+            symlinks: SymlinkPolicy::default(),
+            include_hidden: true,
+            sniff_mime: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SymlinkPolicy {
+    /// 跟随符号链接，通过设备号与 inode 检测循环
+    #[default]
+    Follow,
+    Skip,
+    /// 不跟随，作为记录链接目标的小对象上传
+    Redirect,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum SkipReason {
+    Symlink,
+    BrokenSymlink,
+    // 目录已经扫描过，通常是符号链接形成了循环
+    SymlinkLoop,
+    // FIFO、套接字、设备等非普通文件
+    Special { kind: String },
+    Unreadable { message: String },
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedEntry {
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+pub enum ScanEntry {
+    File(FileDetail),
+    Skipped(SkippedEntry),
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanBatch {
+    pub scan_id: String,
+    pub entries: Vec<FileDetail>,
+    pub skipped: Vec<SkippedEntry>,
+    // 截至本批的累计文件数与字节数
+    pub total_files: u64,
+    pub total_bytes: u64,
+    pub total_skipped: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanStatus {
+    Completed,
+    Cancelled,
+    Error { message: String },
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanFinished {
+    pub scan_id: String,
+    pub total_files: u64,
+    pub total_bytes: u64,
+    pub total_skipped: u64,
+    pub status: ScanStatus,
+}
+
+#[cfg(unix)]
+fn unix_mode(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn unix_mode(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn is_hidden(path: &Path, _metadata: &Metadata) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+#[cfg(windows)]
+fn is_hidden(path: &Path, metadata: &Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+        || path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_hidden(path: &Path, _metadata: &Metadata) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+#[cfg(unix)]
+fn special_kind(metadata: &Metadata) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = metadata.file_type();
+    if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() {
+        "blockDevice"
+    } else if file_type.is_char_device() {
+        "charDevice"
+    } else {
+        "other"
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_metadata: &Metadata) -> &'static str {
+    "other"
+}
+
+// 用于检测重复扫描的目录标识：Unix 上为设备号与 inode，其他平台为规范化后的路径
+#[derive(Hash, PartialEq, Eq)]
+enum DirId {
+    #[cfg(unix)]
+    Inode(u64, u64),
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+#[cfg(unix)]
+fn dir_id(_path: &Path, metadata: &Metadata) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    Some(DirId::Inode(metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path, _metadata: &Metadata) -> Option<DirId> {
+    std::fs::canonicalize(path).ok().map(DirId::Path)
+}
+
+fn relative_path(path: &str, base_path: &str) -> String {
+    path.strip_prefix(base_path)
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn modified_secs(metadata: &Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+}
+
+fn skipped(path: &Path, reason: SkipReason) -> ScanEntry {
+    ScanEntry::Skipped(SkippedEntry {
+        path: path.to_string_lossy().to_string(),
+        reason,
+    })
+}
+
+// 以链接本身作为条目，上传时只记录链接目标
+fn symlink_detail(path: &Path, base_path: &str, metadata: &Metadata) -> Result<FileDetail, String> {
+    let target = std::fs::read_link(path)
+        .map_err(|e| format!("无法读取符号链接：{}", e))?
+        .to_string_lossy()
+        .to_string();
+    let path = path.to_string_lossy().to_string();
+    Ok(FileDetail {
+        id: Uuid::new_v4().to_string(),
+        relative_path: relative_path(&path, base_path),
+        is_dir: false,
+        mime_type: Some(source::SYMLINK_MIME.to_string()),
+        size: target.len() as u64,
+        modified: modified_secs(metadata),
+        readonly: metadata.permissions().readonly(),
+        mode: unix_mode(metadata),
+        symlink_target: Some(target),
+        path,
+    })
+}
+
+fn file_detail(path: &Path, base_path: &str, metadata: &Metadata, sniff_mime: bool) -> FileDetail {
+    let path = path.to_string_lossy().to_string();
+    let relative_path = relative_path(&path, base_path);
+
+    let header = if sniff_mime {
+        mime_detect::read_header_blocking(&path).ok()
+    } else {
+        Some(Vec::new())
+    };
+    let mime_type =
+        header.map(|header| mime_detect::detect(&header, &path, None, None, &HashMap::new()));
+
+    FileDetail {
+        id: Uuid::new_v4().to_string(),
+        relative_path,
+        is_dir: false,
+        mime_type,
+        size: metadata.len(),
+        modified: modified_secs(metadata),
+        readonly: metadata.permissions().readonly(),
+        mode: unix_mode(metadata),
+        symlink_target: None,
+        path,
+    }
+}
+
+/// 目录扫描器，包含忽略规则与符号链接、隐藏文件策略
+pub struct Scanner {
+    matcher: IgnoreMatcher,
+    symlinks: SymlinkPolicy,
+    include_hidden: bool,
+    sniff_mime: bool,
+}
+
+impl Scanner {
+    /// 根据扫描选项与存储桶的默认排除规则创建扫描器
+    pub async fn new(path: &str, options: &ScanOptions) -> Result<Self, String> {
+        let rules = match &options.profile_id {
+            Some(profile_id) => profile::get_profile(profile_id).await?.scan_rules,
+            None => ScanRules::default(),
+        };
+        let exclude = rules
+            .exclude
+            .into_iter()
+            .chain(options.exclude.iter().cloned())
+            .collect::<Vec<_>>();
+
+        // 规则相对于扫描的目录；扫描单个文件时相对于其所在目录
+        let path = Path::new(path);
+        let root = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        Ok(Self {
+            matcher: IgnoreMatcher::new(
+                root,
+                &options.include,
+                &exclude,
+                options.respect_ignore_files,
+            )?,
+            symlinks: options.symlinks,
+            include_hidden: options.include_hidden,
+            sniff_mime: options.sniff_mime,
+        })
+    }
+
+    /// 遍历目录下所有未被忽略的文件。使用栈代替递归，避免深层目录导致栈溢出；
+    /// 在阻塞线程中调用，cancelled 被置位时提前返回 Ok(false)。
+    /// 只有用户选择的路径本身无法读取时返回错误，其余无法处理的条目作为跳过的条目报告
+    pub fn walk(
+        &self,
+        path: &str,
+        cancelled: &AtomicBool,
+        visit: &mut dyn FnMut(ScanEntry),
+    ) -> Result<bool, String> {
+        let base_path = Path::new(path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let root = PathBuf::from(path);
+        let mut visited_dirs = HashSet::new();
+        let mut stack: Vec<(PathBuf, Option<Arc<IgnoreChain>>)> = vec![(root.clone(), None)];
+
+        while let Some((current, chain)) = stack.pop() {
+            if cancelled.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            // 用户直接选择的路径总是包含在内
+            let is_root = current == root;
+
+            let link_metadata = match std::fs::symlink_metadata(&current) {
+                Ok(metadata) => metadata,
+                Err(e) if is_root => return Err(format!("无法获取文件元数据：{}", e)),
+                Err(e) => {
+                    visit(skipped(
+                        &current,
+                        SkipReason::Unreadable {
+                            message: e.to_string(),
+                        },
+                    ));
+                    continue;
+                }
+            };
+            if !is_root && !self.include_hidden && is_hidden(&current, &link_metadata) {
+                continue;
+            }
+
+            let is_symlink = link_metadata.file_type().is_symlink();
+            let target_metadata = if is_symlink {
+                std::fs::metadata(&current).ok()
+            } else {
+                Some(link_metadata.clone())
+            };
+            let is_dir = target_metadata.as_ref().is_some_and(|m| m.is_dir());
+            if !is_root && self.matcher.is_ignored(&current, is_dir, chain.as_ref()) {
+                continue;
+            }
+
+            let metadata = match (is_symlink && !is_root, self.symlinks, target_metadata) {
+                (true, SymlinkPolicy::Skip, _) => {
+                    visit(skipped(&current, SkipReason::Symlink));
+                    continue;
+                }
+                (true, SymlinkPolicy::Redirect, _) => {
+                    visit(match symlink_detail(&current, &base_path, &link_metadata) {
+                        Ok(detail) => ScanEntry::File(detail),
+                        Err(message) => skipped(&current, SkipReason::Unreadable { message }),
+                    });
+                    continue;
+                }
+                (_, _, Some(metadata)) => metadata,
+                (_, _, None) if is_root => return Err("无法获取符号链接指向的文件".to_string()),
+                (_, _, None) => {
+                    visit(skipped(&current, SkipReason::BrokenSymlink));
+                    continue;
+                }
+            };
+
+            if metadata.is_dir() {
+                if let Some(id) = dir_id(&current, &metadata) {
+                    if !visited_dirs.insert(id) {
+                        visit(skipped(&current, SkipReason::SymlinkLoop));
+                        continue;
+                    }
+                }
+                let chain = self.matcher.enter_dir(&current, chain);
+                let entries = match std::fs::read_dir(&current) {
+                    Ok(entries) => entries,
+                    Err(e) if is_root => return Err(format!("无法读取目录：{}", e)),
+                    Err(e) => {
+                        visit(skipped(
+                            &current,
+                            SkipReason::Unreadable {
+                                message: e.to_string(),
+                            },
+                        ));
+                        continue;
+                    }
+                };
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => stack.push((entry.path(), chain.clone())),
+                        Err(e) => visit(skipped(
+                            &current,
+                            SkipReason::Unreadable {
+                                message: e.to_string(),
+                            },
+                        )),
+                    }
+                }
+            } else if metadata.is_file() {
+                visit(ScanEntry::File(file_detail(
+                    &current,
+                    &base_path,
+                    &metadata,
+                    self.sniff_mime,
+                )));
+            } else {
+                visit(skipped(
+                    &current,
+                    SkipReason::Special {
+                        kind: special_kind(&metadata).to_string(),
+                    },
+                ));
+            }
+        }
+        Ok(true)
+    }
+}
+
+// 将扫描结果攒成批次发送，避免逐条发送事件拖慢界面
+struct BatchEmitter<'a> {
+    app: &'a AppHandle,
+    scan_id: &'a str,
+    entries: Vec<FileDetail>,
+    skipped: Vec<SkippedEntry>,
+    total_files: u64,
+    total_bytes: u64,
+    total_skipped: u64,
+    last_emit: Instant,
+}
+
+impl<'a> BatchEmitter<'a> {
+    fn new(app: &'a AppHandle, scan_id: &'a str) -> Self {
+        Self {
+            app,
+            scan_id,
+            entries: Vec::with_capacity(BATCH_SIZE),
+            skipped: Vec::new(),
+            total_files: 0,
+            total_bytes: 0,
+            total_skipped: 0,
+            last_emit: Instant::now(),
+        }
+    }
+
+    fn push(&mut self, entry: ScanEntry) {
+        match entry {
+            ScanEntry::File(detail) => {
+                self.total_files += 1;
+                self.total_bytes += detail.size;
+                self.entries.push(detail);
+            }
+            ScanEntry::Skipped(skipped) => {
+                self.total_skipped += 1;
+                self.skipped.push(skipped);
+            }
+        }
+        if self.entries.len() + self.skipped.len() >= BATCH_SIZE
+            || self.last_emit.elapsed() >= BATCH_INTERVAL
+        {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        self.last_emit = Instant::now();
+        if self.entries.is_empty() && self.skipped.is_empty() {
+            return;
+        }
+        let _ = self.app.emit(
+            "scan-batch",
+            ScanBatch {
+                scan_id: self.scan_id.to_string(),
+                entries: std::mem::take(&mut self.entries),
+                skipped: std::mem::take(&mut self.skipped),
+                total_files: self.total_files,
+                total_bytes: self.total_bytes,
+                total_skipped: self.total_skipped,
+            },
+        );
+    }
+}
+
+/// 开始扫描目录，立即返回 scan_id。结果通过 `scan-batch` 事件分批发送，
+/// 结束（完成、取消或出错）时发送 `scan-finished` 事件。
+/// 调用方应传入自己生成的 scan_id 并先监听事件，否则可能错过命令返回前发送的批次
+#[tauri::command]
+pub async fn scan_start(
+    app: AppHandle,
+    path: String,
+    scan_id: Option<String>,
+    options: Option<ScanOptions>,
+) -> Result<String, String> {
+    let scanner = Scanner::new(&path, &options.unwrap_or_default()).await?;
+    let scan_id = scan_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancelled = Arc::new(AtomicBool::new(false));
+    match SCAN_TASKS.entry(scan_id.clone()) {
+        dashmap::Entry::Occupied(_) => return Err(format!("扫描任务已存在：{}", scan_id)),
+        dashmap::Entry::Vacant(entry) => {
+            entry.insert(cancelled.clone());
+        }
+    }
+
+    let id = scan_id.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut batch = BatchEmitter::new(&app, &id);
+        let status = match scanner.walk(&path, &cancelled, &mut |entry| batch.push(entry)) {
+            Ok(true) => ScanStatus::Completed,
+            Ok(false) => ScanStatus::Cancelled,
+            Err(message) => ScanStatus::Error { message },
+        };
+        batch.flush();
+        let (total_files, total_bytes, total_skipped) =
+            (batch.total_files, batch.total_bytes, batch.total_skipped);
+
+        SCAN_TASKS.remove(&id);
+        let _ = app.emit(
+            "scan-finished",
+            ScanFinished {
+                scan_id: id,
+                total_files,
+                total_bytes,
+                total_skipped,
+                status,
+            },
+        );
+    });
+
+    Ok(scan_id)
+}
+
+#[tauri::command]
+pub fn scan_cancel(scan_id: String) -> Result<(), String> {
+    if let Some(cancelled) = SCAN_TASKS.get(&scan_id) {
+        cancelled.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
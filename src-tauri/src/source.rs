@@ -0,0 +1,150 @@
+use crate::typ::UploadSource;
+use base64::{engine::general_purpose, Engine};
+use percent_encoding::percent_decode_str;
+
+/// 符号链接对象的 Content-Type
+pub const SYMLINK_MIME: &str = "inode/symlink";
+// 符号链接目标保存在该元数据中（上传时自动加上 `x-amz-meta-` 前缀）
+pub const SYMLINK_TARGET_META: &str = "r2u-symlink-target";
+
+/// 解码后的内存上传内容
+pub struct InlineContent {
+    pub data: Vec<u8>,
+    /// 内容自带的 MIME 类型，如 data URL 中声明的类型
+    pub content_type: Option<String>,
+}
+
+impl InlineContent {
+    /// 解码内存中的上传内容。文本内容只有是完整的 base64 图片等 data URL 时才按二进制解码，
+    /// 其余以 `data:` 开头的文本原样上传
+    pub fn decode(source: &UploadSource) -> Result<Self, String> {
+        match source {
+            UploadSource::FilePath(path) => Err(format!("{} 是本地文件，不是内存内容", path)),
+            UploadSource::Url(url) => Err(format!("{} 是远程 URL，不是内存内容", url)),
+            UploadSource::FileContent(content) => Ok(parse_base64_data_url(content)
+                .unwrap_or_else(|| Self {
+                    data: content.as_bytes().to_vec(),
+                    content_type: None,
+                })),
+            UploadSource::Base64(content) => match parse_data_url(content) {
+                Some(result) => result,
+                None => Ok(Self {
+                    data: decode_base64(content)?,
+                    content_type: None,
+                }),
+            },
+            UploadSource::Bytes(data) => Ok(Self {
+                data: data.clone(),
+                content_type: None,
+            }),
+            // 对象内容为链接目标
+            UploadSource::Symlink(path) => Ok(Self {
+                data: std::fs::read_link(path)
+                    .map_err(|e| format!("无法读取符号链接：{}", e))?
+                    .to_string_lossy()
+                    .as_bytes()
+                    .to_vec(),
+                content_type: Some(SYMLINK_MIME.to_string()),
+            }),
+        }
+    }
+}
+
+fn decode_base64(content: &str) -> Result<Vec<u8>, String> {
+    let content: String = content.split_whitespace().collect();
+    general_purpose::STANDARD
+        .decode(&content)
+        .or_else(|_| general_purpose::URL_SAFE.decode(&content))
+        .map_err(|e| format!("base64 内容格式错误：{}", e))
+}
+
+// 粘贴的文本只在声明了 `type/subtype` 与 base64 且能解码时才视为 data URL
+fn parse_base64_data_url(content: &str) -> Option<InlineContent> {
+    let (meta, _) = content.strip_prefix("data:")?.split_once(',')?;
+    let mut params = meta.split(';');
+    if !params.next().is_some_and(is_mime_type) {
+        return None;
+    }
+    if !params.any(|param| param.trim().eq_ignore_ascii_case("base64")) {
+        return None;
+    }
+    parse_data_url(content)?.ok()
+}
+
+fn is_mime_type(mime: &str) -> bool {
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+    };
+    matches!(mime.trim().split_once('/'), Some((kind, subtype)) if is_token(kind) && is_token(subtype))
+}
+
+// 解析 `data:[<mime>][;参数][;base64],<数据>`，不是 data URL 时返回 None
+fn parse_data_url(content: &str) -> Option<Result<InlineContent, String>> {
+    let (meta, data) = content.strip_prefix("data:")?.split_once(',')?;
+    let mut params = meta.split(';');
+    let content_type = params
+        .next()
+        .map(|mime| mime.trim().to_lowercase())
+        .filter(|mime| mime.contains('/'));
+    let is_base64 = params.any(|param| param.trim().eq_ignore_ascii_case("base64"));
+
+    let data = if is_base64 {
+        match decode_base64(data) {
+            Ok(data) => data,
+            Err(e) => return Some(Err(e)),
+        }
+    } else {
+        percent_decode_str(data).collect()
+    };
+    Some(Ok(InlineContent { data, content_type }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_url() {
+        let content = parse_data_url("data:Text/Plain;charset=utf-8,a%20b")
+            .unwrap()
+            .unwrap();
+        assert_eq!(content.data, b"a b");
+        assert_eq!(content.content_type.as_deref(), Some("text/plain"));
+
+        let content = parse_data_url("data:image/png;base64,aGVs\nbG8=")
+            .unwrap()
+            .unwrap();
+        assert_eq!(content.data, b"hello");
+        assert_eq!(content.content_type.as_deref(), Some("image/png"));
+
+        let content = parse_data_url("data:;base64,aGVsbG8").unwrap();
+        assert!(content.is_err());
+        let content = parse_data_url("data:,hello").unwrap().unwrap();
+        assert_eq!(content.content_type, None);
+
+        assert!(parse_data_url("hello").is_none());
+        assert!(parse_data_url("data:text/plain").is_none());
+    }
+
+    #[test]
+    fn base64_data_url() {
+        let content = parse_base64_data_url("data:image/png;base64,aGVsbG8=").unwrap();
+        assert_eq!(content.data, b"hello");
+
+        // 粘贴的普通文本不应被当作 data URL 解码
+        assert!(parse_base64_data_url("data:text/plain,hello").is_none());
+        assert!(parse_base64_data_url("data: see below;base64, text").is_none());
+        assert!(parse_base64_data_url("data:image/png;base64,not base64!").is_none());
+    }
+
+    #[test]
+    fn decode_file_content() {
+        let source = UploadSource::FileContent("data:, not a data url".to_string());
+        let content = InlineContent::decode(&source).unwrap();
+        assert_eq!(content.data, b"data:, not a data url");
+        assert_eq!(content.content_type, None);
+    }
+}
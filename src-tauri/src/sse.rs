@@ -0,0 +1,101 @@
+use crate::crypto;
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::copy_object::builders::CopyObjectFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::get_object::builders::GetObjectFluentBuilder;
+use aws_sdk_s3::operation::head_object::builders::HeadObjectFluentBuilder;
+use aws_sdk_s3::operation::put_object::builders::PutObjectFluentBuilder;
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use base64::{engine::general_purpose, Engine};
+use md5::{Digest, Md5};
+
+// SSE-C 仅支持 AES256
+const ALGORITHM: &str = "AES256";
+
+/// SSE-C 客户提供的密钥，请求时需要同时携带密钥与其 MD5
+#[derive(Clone)]
+pub struct SseCustomerKey {
+    key: String,
+    key_md5: String,
+}
+
+impl std::fmt::Debug for SseCustomerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SseCustomerKey").finish_non_exhaustive()
+    }
+}
+
+impl SseCustomerKey {
+    /// 从 base64 编码的 256 位密钥创建
+    pub fn from_base64(key: &str) -> Result<Self, String> {
+        let raw = general_purpose::STANDARD
+            .decode(key.trim())
+            .map_err(|e| format!("SSE-C 密钥格式错误：{}", e))?;
+        if raw.len() != crypto::KEY_LEN {
+            return Err(format!(
+                "SSE-C 密钥长度必须为 {} 字节，当前为 {} 字节",
+                crypto::KEY_LEN,
+                raw.len()
+            ));
+        }
+        Ok(Self {
+            key: general_purpose::STANDARD.encode(&raw),
+            key_md5: general_purpose::STANDARD.encode(Md5::digest(&raw)),
+        })
+    }
+}
+
+pub trait ApplySseCustomerKey {
+    fn sse_customer_key_opt(self, key: Option<&SseCustomerKey>) -> Self;
+}
+
+macro_rules! impl_apply_sse_customer_key {
+    ($($builder:ty),*) => {
+        $(
+            impl ApplySseCustomerKey for $builder {
+                fn sse_customer_key_opt(self, key: Option<&SseCustomerKey>) -> Self {
+                    match key {
+                        Some(key) => self
+                            .sse_customer_algorithm(ALGORITHM)
+                            .sse_customer_key(&key.key)
+                            .sse_customer_key_md5(&key.key_md5),
+                        None => self,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_apply_sse_customer_key!(
+    PutObjectFluentBuilder,
+    CreateMultipartUploadFluentBuilder,
+    UploadPartFluentBuilder,
+    CompleteMultipartUploadFluentBuilder,
+    HeadObjectFluentBuilder,
+    GetObjectFluentBuilder,
+    CopyObjectFluentBuilder
+);
+
+/// 复制对象时，源对象同样需要提供密钥
+pub trait ApplyCopySourceSseCustomerKey {
+    fn copy_source_sse_customer_key_opt(self, key: Option<&SseCustomerKey>) -> Self;
+}
+
+impl ApplyCopySourceSseCustomerKey for CopyObjectFluentBuilder {
+    fn copy_source_sse_customer_key_opt(self, key: Option<&SseCustomerKey>) -> Self {
+        match key {
+            Some(key) => self
+                .copy_source_sse_customer_algorithm(ALGORITHM)
+                .copy_source_sse_customer_key(&key.key)
+                .copy_source_sse_customer_key_md5(&key.key_md5),
+            None => self,
+        }
+    }
+}
+
+/// 生成一个随机的 SSE-C 密钥（base64）
+#[tauri::command]
+pub fn sse_generate_key() -> String {
+    general_purpose::STANDARD.encode(crypto::random_bytes::<{ crypto::KEY_LEN }>())
+}
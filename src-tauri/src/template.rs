@@ -0,0 +1,35 @@
+/// 渲染 `{变量}` 形式的模板，`{{` 与 `}}` 分别输出字面量 `{` 与 `}`。
+/// lookup 接收去掉首尾空白的变量内容（如 `random:8`）与已渲染的内容，将变量的值追加到后者
+pub fn render<F>(template: &str, mut lookup: F) -> Result<String, String>
+where
+    F: FnMut(&str, &mut String) -> Result<(), String>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut var = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => var.push(c),
+                        None => return Err(format!("模板变量未闭合：{{{}", var)),
+                    }
+                }
+                lookup(var.trim(), &mut out)?;
+            }
+            _ => out.push(c),
+        }
+    }
+    Ok(out)
+}
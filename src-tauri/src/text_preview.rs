@@ -0,0 +1,211 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// UTF-16 判断时至少需要的字节数
+const UTF16_SAMPLE_LEN: usize = 64;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextPreview {
+    pub content: String,
+    /// 检测到的字符集，如 UTF-8、GBK、Shift_JIS
+    pub encoding: String,
+    /// 用于语法高亮的语言，如 rust、python
+    pub language: Option<String>,
+    /// 文件超出行数或字节数限制，只返回了开头部分
+    pub truncated: bool,
+}
+
+/// 判断文件头是否为文本：UTF-16 编码，或者不含 NUL 且几乎没有其他控制字符
+pub fn is_text(header: &[u8]) -> bool {
+    if header.is_empty() {
+        return false;
+    }
+    if detect_utf16(header).is_some() {
+        return true;
+    }
+    let controls = header
+        .iter()
+        .filter(|b| **b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    !header.contains(&0) && controls * 100 <= header.len()
+}
+
+/// 只读取文件开头不超过 max_bytes 字节并转码为 UTF-8，最多保留 max_lines 行
+pub fn read_head(
+    path: &str,
+    size: u64,
+    max_lines: usize,
+    max_bytes: usize,
+) -> Result<TextPreview, String> {
+    let file = File::open(path).map_err(|e| format!("无法读取文件：{}", e))?;
+    let mut head = Vec::with_capacity(max_bytes.min(size as usize));
+    file.take(max_bytes as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("无法读取文件：{}", e))?;
+    let complete = head.len() as u64 >= size;
+
+    let (encoding, bom_len) = match Encoding::for_bom(&head) {
+        Some((encoding, bom_len)) => (encoding, bom_len),
+        None => (detect_encoding(&head, complete), 0),
+    };
+    let (decoded, _) = encoding.decode_without_bom_handling(&head[bom_len..]);
+    let mut content = decoded.as_ref();
+    // 按字节截断时丢弃最后不完整的一行
+    if !complete {
+        if let Some(end) = content.rfind('\n') {
+            content = &content[..end];
+        }
+    }
+
+    let mut lines = content.lines();
+    let text = lines
+        .by_ref()
+        .take(max_lines)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let truncated = !complete || lines.next().is_some();
+
+    Ok(TextPreview {
+        language: detect_language(path, &text),
+        content: text,
+        encoding: encoding.name().to_string(),
+        truncated,
+    })
+}
+
+fn detect_encoding(head: &[u8], complete: bool) -> &'static Encoding {
+    if let Some(encoding) = detect_utf16(head) {
+        return encoding;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(head, complete);
+    detector.guess(None, true)
+}
+
+// 没有 BOM 的 UTF-16 文件中，ASCII 字符的高字节为 0，NUL 集中在偶数或奇数位置
+fn detect_utf16(head: &[u8]) -> Option<&'static Encoding> {
+    if head.len() < UTF16_SAMPLE_LEN {
+        return None;
+    }
+    let sample = &head[..head.len().min(4096) & !1];
+    let pairs = sample.len() / 2;
+    let even = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+    match (even * 10 / pairs, odd * 10 / pairs) {
+        (e, 0) if e >= 3 => Some(UTF_16BE),
+        (0, o) if o >= 3 => Some(UTF_16LE),
+        _ => None,
+    }
+}
+
+/// 根据文件名、扩展名或 shebang 推断语言
+pub fn detect_language(path: &str, content: &str) -> Option<String> {
+    let path = Path::new(path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let by_name = match name.as_str() {
+        "dockerfile" | "containerfile" => Some("dockerfile"),
+        "makefile" | "gnumakefile" => Some("makefile"),
+        "cmakelists.txt" => Some("cmake"),
+        ".bashrc" | ".bash_profile" | ".profile" | ".zshrc" => Some("bash"),
+        ".gitignore" | ".r2ignore" | ".dockerignore" => Some("ignore"),
+        _ => None,
+    };
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    by_name
+        .or_else(|| language_by_extension(&extension))
+        .or_else(|| language_by_shebang(content))
+        .map(str::to_string)
+}
+
+fn language_by_extension(extension: &str) -> Option<&'static str> {
+    let language = match extension {
+        "rs" => "rust",
+        "py" | "pyw" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "json" | "jsonc" | "json5" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "xml" | "svg" | "plist" => "xml",
+        "html" | "htm" | "xhtml" => "html",
+        "css" => "css",
+        "scss" | "sass" => "scss",
+        "less" => "less",
+        "md" | "markdown" => "markdown",
+        "sh" | "bash" | "zsh" => "bash",
+        "fish" => "fish",
+        "ps1" | "psm1" => "powershell",
+        "bat" | "cmd" => "batch",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "m" | "mm" => "objectivec",
+        "rb" => "ruby",
+        "php" => "php",
+        "pl" | "pm" => "perl",
+        "lua" => "lua",
+        "r" => "r",
+        "dart" => "dart",
+        "scala" => "scala",
+        "sql" => "sql",
+        "ini" | "cfg" | "conf" => "ini",
+        "properties" => "properties",
+        "diff" | "patch" => "diff",
+        "tex" => "latex",
+        "csv" => "csv",
+        "log" => "log",
+        _ => return None,
+    };
+    Some(language)
+}
+
+// 如 `#!/usr/bin/env python3`、`#!/bin/bash`
+fn language_by_shebang(content: &str) -> Option<&'static str> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut parts = line.split_whitespace();
+    let mut program = parts.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = parts.find(|part| !part.starts_with('-'))?;
+    }
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let language = match program {
+        "sh" | "bash" | "dash" | "ksh" | "zsh" => "bash",
+        "fish" => "fish",
+        "python" => "python",
+        "node" | "nodejs" => "javascript",
+        "deno" | "bun" | "ts-node" => "typescript",
+        "ruby" => "ruby",
+        "perl" => "perl",
+        "php" => "php",
+        "lua" => "lua",
+        "Rscript" => "r",
+        "pwsh" => "powershell",
+        _ => return None,
+    };
+    Some(language)
+}
@@ -0,0 +1,201 @@
+use crate::image_pipeline;
+use dashmap::DashSet;
+use image::ImageFormat;
+use once_cell::sync::Lazy;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeResponder};
+use uuid::Uuid;
+
+/// 缩略图自定义协议，地址形如 `thumb://localhost/<编码后的本地路径>?size=512`
+pub const SCHEME: &str = "thumb";
+
+const DEFAULT_SIZE: u32 = 512;
+const MIN_SIZE: u32 = 16;
+const MAX_SIZE: u32 = 2048;
+// 原图需要整体读入内存解码
+const MAX_SOURCE_SIZE: u64 = 100 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 80;
+const CACHE_DIR: &str = "thumbnails";
+// 缓存目录超过该大小时删除最久未使用的缩略图
+const MAX_CACHE_SIZE: u64 = 200 * 1024 * 1024;
+
+// 只为通过 thumbnail_url 生成过地址的文件提供缩略图，即用户选择或扫描后预览的文件，
+// 避免网页内容通过协议读取任意本地图片
+static ALLOWED_PATHS: Lazy<DashSet<String>> = Lazy::new(DashSet::new);
+
+/// 生成前端可直接用于 `<img>` 的缩略图地址
+pub fn thumbnail_url(path: &str, max_dimension: Option<u32>) -> String {
+    ALLOWED_PATHS.insert(path.to_string());
+    let path = utf8_percent_encode(path, NON_ALPHANUMERIC);
+    let size = clamp_size(max_dimension);
+    // Windows 与 Android 的 WebView 不支持自定义 scheme，Tauri 将其映射为 http://<scheme>.localhost
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/{}?size={}", SCHEME, path, size)
+    } else {
+        format!("{}://localhost/{}?size={}", SCHEME, path, size)
+    }
+}
+
+fn clamp_size(max_dimension: Option<u32>) -> u32 {
+    max_dimension
+        .unwrap_or(DEFAULT_SIZE)
+        .clamp(MIN_SIZE, MAX_SIZE)
+}
+
+fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(CACHE_DIR))
+        .map_err(|e| format!("无法获取应用缓存目录：{}", e))
+}
+
+/// 处理缩略图协议请求，生成缩略图在阻塞线程中进行
+pub fn handle_request(app: AppHandle, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = parse_request(&request).and_then(|(path, size)| {
+            let dir = cache_dir(&app)?;
+            get_or_create(&dir, &path, size)
+        });
+        let response = match result {
+            Ok((data, content_type)) => Response::builder()
+                .header(header::CONTENT_TYPE, content_type)
+                .header(header::CACHE_CONTROL, "max-age=3600")
+                .body(data),
+            Err(e) => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+                .body(e.into_bytes()),
+        };
+        if let Ok(response) = response {
+            responder.respond(response);
+        }
+    });
+}
+
+fn parse_request(request: &Request<Vec<u8>>) -> Result<(String, u32), String> {
+    let uri = request.uri();
+    let path = percent_decode_str(uri.path().trim_start_matches('/'))
+        .decode_utf8()
+        .map_err(|e| format!("缩略图路径无效：{}", e))?
+        .into_owned();
+    let size = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|param| param.strip_prefix("size="))
+        .and_then(|size| size.parse().ok());
+    if !ALLOWED_PATHS.contains(&path) {
+        return Err("文件未被选择，无法生成缩略图".to_string());
+    }
+    Ok((path, clamp_size(size)))
+}
+
+/// 读取缓存的缩略图，不存在时生成。缓存按路径、修改时间、大小与尺寸区分，文件修改后自动失效
+pub fn get_or_create(
+    cache_dir: &Path,
+    path: &str,
+    size: u32,
+) -> Result<(Vec<u8>, &'static str), String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("无法获取文件元数据：{}", e))?;
+    if metadata.len() > MAX_SOURCE_SIZE {
+        return Err(format!(
+            "文件大小超过 {}MB 限制",
+            MAX_SOURCE_SIZE / 1024 / 1024
+        ));
+    }
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    let cache_key = format!(
+        "{:x}",
+        Sha256::digest(format!(
+            "{}\0{}\0{}\0{}",
+            path,
+            modified,
+            metadata.len(),
+            size
+        ))
+    );
+
+    for (extension, content_type) in [("jpg", "image/jpeg"), ("png", "image/png")] {
+        let cache_path = cache_dir.join(format!("{}.{}", cache_key, extension));
+        if let Ok(data) = std::fs::read(&cache_path) {
+            // 更新修改时间，清理缓存时按修改时间淘汰
+            let _ = std::fs::File::options()
+                .write(true)
+                .open(&cache_path)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            return Ok((data, content_type));
+        }
+    }
+
+    let data = std::fs::read(path).map_err(|e| format!("无法读取图片文件：{}", e))?;
+    let format = image::guess_format(&data).map_err(|e| format!("无法识别图片格式：{}", e))?;
+    let image = image_pipeline::decode(&data, format)?;
+    let image = if image.width().max(image.height()) > size {
+        image.thumbnail(size, size)
+    } else {
+        image
+    };
+    // 有透明通道的图片使用 PNG，避免透明区域变黑
+    let format = if image.color().has_alpha() {
+        ImageFormat::Png
+    } else {
+        ImageFormat::Jpeg
+    };
+    let thumbnail = image_pipeline::encode(&image, format, JPEG_QUALITY)?;
+    let (content_type, extension) = image_pipeline::format_info(format);
+
+    // 缓存写入失败不影响本次返回
+    let cache_path = cache_dir.join(format!("{}.{}", cache_key, extension));
+    let tmp_path = cache_dir.join(format!("{}.tmp", Uuid::new_v4()));
+    let _ = std::fs::create_dir_all(cache_dir)
+        .and_then(|_| std::fs::write(&tmp_path, &thumbnail))
+        .and_then(|_| std::fs::rename(&tmp_path, &cache_path));
+    prune_cache(cache_dir, MAX_CACHE_SIZE);
+    Ok((thumbnail, content_type))
+}
+
+// 缓存超过上限时按修改时间从旧到新删除，直到低于上限
+fn prune_cache(cache_dir: &Path, max_size: u64) {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return;
+    };
+    let mut files = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect::<Vec<_>>();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= max_size {
+        return;
+    }
+    files.sort_by_key(|(modified, _, _)| *modified);
+    for (_, size, path) in files {
+        if total <= max_size {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= size;
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn thumbnail_clear_cache(app: AppHandle) -> Result<(), String> {
+    let dir = cache_dir(&app)?;
+    match tokio::fs::remove_dir_all(&dir).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("无法清除缩略图缓存：{}", e)),
+    }
+}
@@ -1,10 +1,34 @@
+use crate::image_pipeline::OptimizationReport;
+use crate::link_format::FormattedLink;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum UploadSource {
     FilePath(String),
+    // 文本内容，`data:` 开头时按 data URL 解码
     FileContent(String),
+    // base64 或 data URL 编码的二进制内容
+    Base64(String),
+    // 原始字节
+    Bytes(Vec<u8>),
+    // 符号链接的路径，只上传记录链接目标的小对象
+    Symlink(String),
+    // http/https URL，响应体直接流式上传
+    Url(String),
+}
+
+impl UploadSource {
+    // 历史记录与批次报告中的来源路径，远程上传为 URL
+    pub fn local_path(&self) -> Option<&str> {
+        match self {
+            UploadSource::FilePath(path)
+            | UploadSource::Symlink(path)
+            | UploadSource::Url(path) => Some(path),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -13,15 +37,63 @@ pub struct File {
     pub id: String,
     pub source: UploadSource,
     pub remote_filename: String,
+    // 覆盖存储桶默认的 key 模板
+    #[serde(default)]
+    pub key_template: Option<String>,
+    // 覆盖存储桶默认的对象头
+    #[serde(default)]
+    pub headers: ObjectHeaders,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentDisposition {
+    Inline,
+    Attachment,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ObjectHeaders {
+    pub cache_control: Option<String>,
+    // 使用原始文件名作为 filename 参数
+    pub content_disposition: Option<ContentDisposition>,
+    pub content_language: Option<String>,
+    // HTTP 日期或 RFC 3339 格式
+    pub expires: Option<String>,
+    // 用户自定义元数据，键可以带或不带 `x-amz-meta-` 前缀
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct FileDetail {
     pub id: String,
     pub path: String,
     pub relative_path: String,
     pub is_dir: bool,
+    // 按扩展名推断的 MIME 类型，扫描时开启 sniffMime 则根据文件头嗅探
+    pub mime_type: Option<String>,
+    pub size: u64,
+    // 修改时间，Unix 时间戳（秒）
+    pub modified: Option<u64>,
+    pub readonly: bool,
+    // Unix 权限位，其他平台为空
+    pub mode: Option<u32>,
+    // 按 redirect 策略扫描到的符号链接的目标，上传时使用 UploadSource::Symlink
+    pub symlink_target: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectInfo {
+    pub key: String,
+    pub url: String,
+    pub size: u64,
+    pub content_type: Option<String>,
+    pub e_tag: Option<String>,
+    pub last_modified: Option<i64>,
+    pub metadata: HashMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -51,4 +123,57 @@ pub struct UploadHistory {
     pub url: String,
     pub status: UploadStatus,
     pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<UploadDetails>,
+}
+
+/// 上传失败准备重试时通过 upload-retry 事件发送
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadRetry {
+    pub file_id: String,
+    // 第几次重试，从 1 开始
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_secs: u64,
+    pub error: String,
+}
+
+/// 上传前处理的结果，随上传完成事件一起返回
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDetails {
+    // 是否去除了 EXIF、GPS、作者等隐私元数据
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metadata_stripped: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimization: Option<OptimizationReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<VariantLink>,
+    // 上传内容（压缩、加密前）的 SHA-256
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // Markdown、HTML 等格式的链接，存储桶配置的默认格式排在第一个
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<FormattedLink>,
+    // 上传失败后重试的次数
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+    // 跳过的处理步骤，如文件过大未去除元数据
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantLink {
+    pub key: String,
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    pub size: u64,
 }
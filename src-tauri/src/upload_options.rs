@@ -0,0 +1,227 @@
+use crate::compression::CompressionPlan;
+use crate::encryption::ObjectCipher;
+use crate::typ::{ContentDisposition, File, ObjectHeaders, UploadSource};
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::put_object::builders::PutObjectFluentBuilder;
+use aws_sdk_s3::primitives::{DateTime, DateTimeFormat};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
+use std::collections::HashMap;
+
+const METADATA_PREFIX: &str = "x-amz-meta-";
+// 含非 ASCII 字符的元数据值百分号编码后保存，同时编码 `%` 以便还原
+const METADATA_VALUE: &AsciiSet = &CONTROLS.add(b'%');
+
+impl ObjectHeaders {
+    /// 以 defaults 为基础合并，当前值优先；metadata 按键合并
+    pub fn merged(&self, defaults: &ObjectHeaders) -> ObjectHeaders {
+        let mut metadata = defaults.metadata.clone();
+        metadata.extend(self.metadata.clone());
+        ObjectHeaders {
+            cache_control: self
+                .cache_control
+                .clone()
+                .or_else(|| defaults.cache_control.clone()),
+            content_disposition: self.content_disposition.or(defaults.content_disposition),
+            content_language: self
+                .content_language
+                .clone()
+                .or_else(|| defaults.content_language.clone()),
+            expires: self.expires.clone().or_else(|| defaults.expires.clone()),
+            metadata,
+        }
+    }
+}
+
+/// 单个对象上传时附带的选项
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    pub content_type: String,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_language: Option<String>,
+    pub expires: Option<DateTime>,
+    pub metadata: HashMap<String, String>,
+    pub content_encoding: Option<String>,
+    // 上传前压缩，为空时不压缩
+    pub compression: Option<CompressionPlan>,
+    // 客户端加密，为空时不加密
+    pub cipher: Option<ObjectCipher>,
+}
+
+fn parse_expires(value: &str) -> Result<DateTime, String> {
+    DateTime::from_str(value, DateTimeFormat::HttpDate)
+        .or_else(|_| DateTime::from_str(value, DateTimeFormat::DateTime))
+        .map_err(|_| format!("Expires 格式无效：{}", value))
+}
+
+// 同时提供 ASCII 回退和 RFC 5987 编码的 filename*
+fn content_disposition(kind: ContentDisposition, filename: &str) -> String {
+    let kind = match kind {
+        ContentDisposition::Inline => "inline",
+        ContentDisposition::Attachment => "attachment",
+    };
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        kind,
+        fallback,
+        utf8_percent_encode(filename, NON_ALPHANUMERIC)
+    )
+}
+
+// HTTP 请求头名称允许的字符（RFC 9110 token）
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// 规范化自定义元数据：键转为小写并去掉 `x-amz-meta-` 前缀，只能包含请求头名称允许的字符；
+/// 值含非 ASCII 或控制字符时进行百分号编码，其他值保持不变
+pub fn normalize_metadata(
+    metadata: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    let mut normalized = HashMap::with_capacity(metadata.len());
+    for (key, value) in metadata {
+        let key = key.trim().to_lowercase();
+        let key = key.strip_prefix(METADATA_PREFIX).unwrap_or(&key);
+        if key.is_empty() {
+            continue;
+        }
+        if !key.chars().all(is_token_char) {
+            return Err(format!(
+                "元数据键 {} 无效，只能包含字母、数字与 !#$%&'*+-.^_`|~",
+                key
+            ));
+        }
+        let value = if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+            value.clone()
+        } else {
+            utf8_percent_encode(value, METADATA_VALUE).to_string()
+        };
+        normalized.insert(key.to_string(), value);
+    }
+    Ok(normalized)
+}
+
+/// 原始文件名：本地文件与符号链接取文件名，其他内容取前端传入 key 的最后一段
+pub fn original_filename(file: &File) -> String {
+    let name = match &file.source {
+        UploadSource::FilePath(path) | UploadSource::Symlink(path) => std::path::Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string()),
+        _ => None,
+    };
+    name.unwrap_or_else(|| {
+        file.remote_filename
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    })
+}
+
+impl UploadOptions {
+    pub fn new(
+        content_type: String,
+        original_filename: &str,
+        headers: &ObjectHeaders,
+    ) -> Result<Self, String> {
+        let metadata = normalize_metadata(&headers.metadata)?;
+
+        Ok(Self {
+            content_type,
+            cache_control: headers.cache_control.clone(),
+            content_disposition: headers
+                .content_disposition
+                .map(|kind| content_disposition(kind, original_filename)),
+            content_language: headers.content_language.clone(),
+            expires: headers.expires.as_deref().map(parse_expires).transpose()?,
+            metadata,
+            content_encoding: None,
+            compression: None,
+            cipher: None,
+        })
+    }
+
+    /// 重新上传前更换加密器，并同步更新元数据中的对象密钥参数
+    pub fn renew_cipher(&self) -> Self {
+        let mut options = self.clone();
+        if let Some(cipher) = &self.cipher {
+            let cipher = cipher.renew();
+            options.metadata.extend(cipher.key_metadata());
+            options.cipher = Some(cipher);
+        }
+        options
+    }
+}
+
+pub trait ApplyUploadOptions {
+    fn upload_options(self, options: &UploadOptions) -> Self;
+}
+
+// put_object 与 create_multipart_upload 的 builder 是不同类型，但设置对象头的方法一致
+macro_rules! impl_apply_upload_options {
+    ($($builder:ty),*) => {
+        $(
+            impl ApplyUploadOptions for $builder {
+                fn upload_options(self, options: &UploadOptions) -> Self {
+                    self.content_type(&options.content_type)
+                        .set_cache_control(options.cache_control.clone())
+                        .set_content_disposition(options.content_disposition.clone())
+                        .set_content_language(options.content_language.clone())
+                        .set_content_encoding(options.content_encoding.clone())
+                        .set_expires(options.expires)
+                        .set_metadata(if options.metadata.is_empty() {
+                            None
+                        } else {
+                            Some(options.metadata.clone())
+                        })
+                }
+            }
+        )*
+    };
+}
+
+impl_apply_upload_options!(PutObjectFluentBuilder, CreateMultipartUploadFluentBuilder);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(pairs: &[(&str, &str)]) -> Result<HashMap<String, String>, String> {
+        normalize_metadata(
+            &pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn normalizes_metadata() {
+        let metadata = normalize(&[
+            ("X-Amz-Meta-Author", "Alice"),
+            (" Title ", "标题 50%"),
+            ("", "ignored"),
+        ])
+        .unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata["author"], "Alice");
+        assert_eq!(metadata["title"], "%E6%A0%87%E9%A2%98 50%25");
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        assert!(normalize(&[("my key", "a")]).is_err());
+        assert!(normalize(&[("作者", "a")]).is_err());
+        assert!(normalize(&[("a:b", "a")]).is_err());
+    }
+}
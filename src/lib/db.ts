@@ -1,8 +1,9 @@
 import Dexie from "dexie";
-import type { AppSettings, Bucket, UploadHistory } from "./type";
+import type { AppSettings, LegacyBucket, UploadHistory } from "./type";
 
 class AppDatabase extends Dexie {
-  buckets!: Dexie.Table<Bucket, number>;
+  // 旧版本明文保存的存储桶，启动时迁移到 Rust 端的配置存储后清空
+  buckets!: Dexie.Table<LegacyBucket, number>;
   history!: Dexie.Table<UploadHistory, number>;
   appSettings!: Dexie.Table<AppSettings, number>;
 
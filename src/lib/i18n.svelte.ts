@@ -77,6 +77,12 @@ export let en = $state({
     setDefault: "Set as Default",
     edit: "Edit",
   },
+  profile: {
+    locked:
+      "Bucket settings are encrypted. Enter the master passphrase to unlock them, or choose one to create a new store.",
+    passphrase: "Master passphrase",
+    unlock: "Unlock",
+  },
   uploadTargetSelector: {
     title: "Bucket",
     placeholder: "Select Bucket",
@@ -99,6 +105,10 @@ export let en = $state({
       filePreview: "File Preview",
       filename: "Filename:",
       remotePath: "Remote Path:",
+      pages: "Pages",
+      duration: "Duration",
+      entries: "Entries",
+      truncated: "Only the beginning is shown",
     },
     previewFailed: "Preview failed",
     uploadSuccess: "Upload successful",
@@ -162,6 +172,8 @@ export let en = $state({
   },
   tools: {
     getFileDetailsFailed: "Failed to get file details",
+    scanning: "Scanning folder, files found:",
+    cancelScan: "Stop scanning",
   },
   transfer: {
     title: "Transfer",
@@ -247,6 +259,11 @@ export let zh = $state({
     setDefault: "设为默认",
     edit: "编辑",
   },
+  profile: {
+    locked: "存储桶配置已加密，请输入主口令解锁；首次使用时输入的口令将用于创建配置存储。",
+    passphrase: "主口令",
+    unlock: "解锁",
+  },
   uploadTargetSelector: {
     title: "存储桶",
     placeholder: "选择存储桶",
@@ -269,6 +286,10 @@ export let zh = $state({
       filePreview: "文件预览",
       filename: "文件名：",
       remotePath: "远程路径：",
+      pages: "页数",
+      duration: "时长",
+      entries: "文件数",
+      truncated: "只显示了开头部分",
     },
     previewFailed: "预览失败",
     uploadSuccess: "上传成功",
@@ -332,6 +353,8 @@ export let zh = $state({
   },
   tools: {
     getFileDetailsFailed: "获取文件详情失败",
+    scanning: "正在扫描文件夹，已找到文件：",
+    cancelScan: "停止扫描",
   },
   transfer: {
     title: "传输",
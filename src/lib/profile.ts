@@ -0,0 +1,64 @@
+import { invoke } from "@tauri-apps/api/core";
+import db from "./db";
+import { globalState } from "./store.svelte";
+import type { BucketProfile, ProfileStoreStatus } from "./type";
+
+// 启动时解锁配置存储：优先使用系统钥匙串，否则等待用户输入主口令
+export async function initProfiles() {
+  const status = await invoke<ProfileStoreStatus>("profile_status");
+  if (!status.unlocked) {
+    const useKeyring = status.initialized
+      ? status.keySource === "keyring"
+      : status.keyringAvailable;
+    if (!useKeyring) {
+      globalState.profileLocked = true;
+      return;
+    }
+    await invoke("profile_unlock", {});
+  }
+  await onUnlocked();
+}
+
+// 使用主口令解锁，配置存储不存在时以该口令创建
+export async function unlockProfiles(passphrase: string) {
+  await invoke("profile_unlock", { passphrase });
+  await onUnlocked();
+}
+
+async function onUnlocked() {
+  globalState.profileLocked = false;
+  await migrateLegacyBuckets();
+  await refreshProfiles();
+}
+
+export async function refreshProfiles() {
+  globalState.profiles = await invoke<BucketProfile[]>("profile_list");
+  // 默认存储桶被删除时清空
+  const defaultId = globalState.appSetting.defaultBucketId;
+  if (defaultId && !globalState.profiles.some((p) => p.id === defaultId)) {
+    globalState.appSetting.defaultBucketId = undefined;
+  }
+}
+
+// 将旧版本保存在 IndexedDB 中的存储桶迁移到配置存储，迁移后删除明文密钥
+async function migrateLegacyBuckets() {
+  const buckets = await db.buckets.toArray();
+  for (const bucket of buckets) {
+    const profile = await invoke<BucketProfile>("profile_add", {
+      profile: {
+        name: bucket.bucketName,
+        provider: "r2",
+        bucketName: bucket.bucketName,
+        accountId: bucket.accountId,
+        accessKey: bucket.accessKey,
+        secretKey: bucket.secretKey,
+        domain: bucket.customDomain || null,
+      },
+    });
+    // 旧的默认存储桶 id 是数字，改为新的 profile id
+    if ((globalState.appSetting.defaultBucketId as unknown) === bucket.id) {
+      globalState.appSetting.defaultBucketId = profile.id;
+    }
+    await db.buckets.delete(bucket.id!);
+  }
+}
@@ -15,6 +15,8 @@ export let globalState: GlobalState = $state({
     onClose: undefined,
   },
   files: [],
+  profileLocked: false,
+  profiles: [],
   selectedBucket: undefined,
   appSetting: {
     sidebarCollapsed: false,
@@ -23,6 +25,7 @@ export let globalState: GlobalState = $state({
     defaultBucketId: undefined,
   },
   progress: {},
+  scans: {},
 });
 
 export function setAlert(message: string) {
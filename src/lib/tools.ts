@@ -1,9 +1,10 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 import { sep } from "@tauri-apps/api/path";
 import clipboard from "tauri-plugin-clipboard-api";
 import { globalState, setAlert } from "./store.svelte";
-import type { FileDetail } from "./type";
+import type { FileDetail, ScanBatch, ScanFinished } from "./type";
 import { t } from "./i18n.svelte";
 
 export function generateTimestamp() {
@@ -23,18 +24,6 @@ function handleRelativePath(path: string) {
     : path.replaceAll(s, "/");
 }
 
-async function getFileDetails(path: string) {
-  try {
-    const details: Array<FileDetail> = await invoke("get_file_details", {
-      path,
-    });
-    return details;
-  } catch (e) {
-    console.error(e);
-    setAlert(t().tools.getFileDetailsFailed);
-  }
-}
-
 function getFileType(path: string): "file" | "text" | "image" {
   const ext = path.split(".").pop()?.toLowerCase();
   if (!ext) return "file";
@@ -54,23 +43,73 @@ function getFileType(path: string): "file" | "text" | "image" {
   return "file";
 }
 
-export async function parsePaths(paths: string[]) {
-  paths.forEach(async (file) => {
-    const details = await getFileDetails(file);
-    if (details && details.length > 0) {
-      details.forEach((detail) => {
-        globalState.files.push({
-          type: getFileType(detail.path),
-          id: detail.id,
-          source: {
-            filePath: detail.path,
-          },
-          remoteFilename: handleRelativePath(detail.relativePath),
-          remoteFilenamePrefix: "",
-        });
-      });
-    }
+function addFileDetails(details: Array<FileDetail>) {
+  globalState.files.push(
+    ...details.map((detail) => ({
+      type: getFileType(detail.path),
+      id: detail.id,
+      source: {
+        filePath: detail.path,
+      },
+      remoteFilename: handleRelativePath(detail.relativePath),
+      remoteFilenamePrefix: "",
+    })),
+  );
+}
+
+// 在后台扫描路径，结果通过 scan-batch 事件分批加入文件列表，大目录不会阻塞界面。
+// 先监听事件再开始扫描，避免错过第一批结果
+async function scanPath(path: string) {
+  const scanId = crypto.randomUUID();
+  globalState.scans[scanId] = { path, totalFiles: 0 };
+
+  const unlistenBatch = await listen<ScanBatch>("scan-batch", (event) => {
+    if (event.payload.scanId !== scanId) return;
+    addFileDetails(event.payload.entries);
+    globalState.scans[scanId].totalFiles = event.payload.totalFiles;
   });
+  const unlistenFinished = await listen<ScanFinished>(
+    "scan-finished",
+    (event) => {
+      if (event.payload.scanId !== scanId) return;
+      cleanup();
+      const status = event.payload.status;
+      if (typeof status === "object" && "error" in status) {
+        console.error(status.error.message);
+        setAlert(t().tools.getFileDetailsFailed);
+      }
+    },
+  );
+  function cleanup() {
+    unlistenBatch();
+    unlistenFinished();
+    delete globalState.scans[scanId];
+  }
+
+  try {
+    await invoke("scan_start", {
+      path,
+      scanId,
+      options: { profileId: globalState.selectedBucket?.value.id },
+    });
+  } catch (e) {
+    cleanup();
+    console.error(e);
+    setAlert(t().tools.getFileDetailsFailed);
+  }
+}
+
+export async function parsePaths(paths: string[]) {
+  await Promise.all(paths.map(scanPath));
+}
+
+// 取消所有正在进行的扫描，已加入列表的文件保留
+export async function cancelScans() {
+  await Promise.all(
+    Object.keys(globalState.scans).map((scanId) =>
+      invoke("scan_cancel", { scanId }),
+    ),
+  );
 }
 
 export function addText(textContent: string, remoteFilename: string) {
@@ -1,7 +1,8 @@
 import type { Selected } from "bits-ui";
 import type { Snippet } from "svelte";
 
-export interface Bucket {
+// 旧版本保存在 IndexedDB 中的存储桶，仅用于迁移到 Rust 端的配置存储
+export interface LegacyBucket {
   id?: number;
   type: "r2" | "s3";
   bucketName: string;
@@ -13,6 +14,28 @@ export interface Bucket {
   [key: string]: string | number | undefined;
 }
 
+// Rust 端保存的存储桶配置，secretKey 等密钥在返回前端时为空
+export interface BucketProfile {
+  id: string;
+  name: string;
+  provider: "r2" | "s3";
+  bucketName: string;
+  accountId: string;
+  endpoint?: string | null;
+  region?: string | null;
+  domain?: string | null;
+  accessKey: string;
+  secretKey: string;
+  [key: string]: unknown;
+}
+
+export interface ProfileStoreStatus {
+  initialized: boolean;
+  unlocked: boolean;
+  keySource: "passphrase" | "keyring" | null;
+  keyringAvailable: boolean;
+}
+
 export interface File {
   type: "text" | "image" | "file";
   id: string;
@@ -21,11 +44,87 @@ export interface File {
   remoteFilenamePrefix: string;
 }
 
+// preview_file 返回的预览，content 只有一个键
+export interface FilePreview {
+  mimeType: string;
+  size: number;
+  content:
+    | { image: { url: string; width: number | null; height: number | null } }
+    | { svg: { dataUrl: string } }
+    | {
+        text: {
+          content: string;
+          encoding: string;
+          language: string | null;
+          truncated: boolean;
+        };
+      }
+    | {
+        pdf: {
+          pageCount: number;
+          version: string;
+          encrypted: boolean;
+          title: string | null;
+          author: string | null;
+          creator: string | null;
+          producer: string | null;
+        };
+      }
+    | {
+        media: {
+          durationSecs: number | null;
+          tracks: Array<{
+            codec: string;
+            sampleRate: number | null;
+            channels: number | null;
+            language: string | null;
+          }>;
+          tags: Record<string, string>;
+          cover: string | null;
+        };
+      }
+    | {
+        archive: {
+          format: string;
+          entries: Array<{ name: string; size: number; isDir: boolean }>;
+          totalEntries: number | null;
+          truncated: boolean;
+        };
+      }
+    | { binary: { hexDump: string } };
+}
+
 export interface FileDetail {
   id: string;
   path: string;
   relativePath: string;
   isDir: boolean;
+  mimeType: string | null;
+  size: number;
+  modified: number | null;
+}
+
+export interface ScanBatch {
+  scanId: string;
+  entries: Array<FileDetail>;
+  skipped: Array<{ path: string; reason: unknown }>;
+  totalFiles: number;
+  totalBytes: number;
+  totalSkipped: number;
+}
+
+export interface ScanFinished {
+  scanId: string;
+  totalFiles: number;
+  totalBytes: number;
+  totalSkipped: number;
+  status: "completed" | "cancelled" | { error: { message: string } };
+}
+
+// 正在进行的目录扫描
+export interface ScanProgress {
+  path: string;
+  totalFiles: number;
 }
 
 export type UploadStatus =
@@ -54,9 +153,14 @@ export interface GlobalState {
   };
   modal: ModalState;
   files: Array<File>;
-  selectedBucket: Selected<Bucket> | undefined;
+  // 配置存储需要主口令解锁
+  profileLocked: boolean;
+  profiles: Array<BucketProfile>;
+  selectedBucket: Selected<BucketProfile> | undefined;
   appSetting: AppSettings;
   progress: Record<string, UploadHistory>;
+  // 键是 scanId
+  scans: Record<string, ScanProgress>;
 }
 
 export interface AppSettings {
@@ -64,7 +168,7 @@ export interface AppSettings {
   sidebarCollapsed: boolean;
   useSystemProxy: boolean;
   locale: string;
-  defaultBucketId: number | undefined;
+  defaultBucketId: string | undefined;
 }
 
 export interface ModalState {